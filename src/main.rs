@@ -1,14 +1,22 @@
-use clap::{Parser, Subcommand};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::fs;
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tui::{
     backend::CrosstermBackend,
-    layout::{Constraint},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Cell, Row, Table},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
     Terminal,
 };
 use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode};
@@ -19,249 +27,9676 @@ use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScree
 #[command(name = "pymanager")]
 #[command(about = "A tool to manage Python environments and projects", long_about = None)]
 struct Cli {
+    /// Answer yes to every confirmation prompt
+    #[arg(long, global = true)]
+    yes: bool,
+    /// Show what would change without writing anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Print debugging notes to stderr
+    #[arg(long, short, global = true)]
+    verbose: bool,
+    /// Stop at the first log that cannot be read instead of skipping it
+    #[arg(long, global = true)]
+    strict: bool,
+    /// Refuse every command that would change a log
+    #[arg(long, global = true)]
+    read_only: bool,
+    /// Keep logs in this directory instead of the per-user data directory;
+    /// overrides PYMANAGER_DATA_DIR
+    #[arg(long, value_name = "DIR", global = true)]
+    data_dir: Option<PathBuf>,
+    /// Store logs in this backend instead of the one in the config file
+    #[arg(long, value_enum, global = true)]
+    backend: Option<StorageBackend>,
+    /// Also search this directory for interpreters (repeatable)
+    #[arg(long = "python-dir", value_name = "DIR", global = true)]
+    python_dirs: Vec<PathBuf>,
+    /// Rescan for interpreters instead of using the cached results
+    #[arg(long, global = true)]
+    refresh: bool,
+    /// Ignore interpreters whose binary name matches this regex (repeatable)
+    #[arg(long, value_name = "PATTERN", value_parser = parse_exclude_pattern, global = true)]
+    exclude: Vec<Regex>,
+    /// Ignore interpreters older than this X.Y version
+    #[arg(long, value_name = "X.Y", value_parser = parse_min_version, global = true)]
+    min_version: Option<PythonVersion>,
     #[command(subcommand)]
     command: Commands,
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    /// List all Python versions available on the system
-    ListPythonVersions,
-    /// List all projects worked on by a specific Python version
-    ListPythonProjects { version: String },
-    /// Add a project to the log for a specific Python version
-    AddProject { version: String, project: String },
-    /// Show projects in a table
-    ShowTable,
+/// Settings shared by every command, built once from the global flags.
+struct Context {
+    /// The subcommand being run, such as `alias add`, for the journal.
+    command: String,
+    yes: bool,
+    dry_run: bool,
+    verbose: bool,
+    /// Fail on an unreadable log where it would otherwise be skipped.
+    strict: bool,
+    /// Listing order when `--sort` is not given.
+    default_sort: Option<SortKey>,
+    theme: TuiTheme,
+    /// Interpreter directories from the config file and `--python-dir`.
+    python_dirs: Vec<PathBuf>,
+    /// Directories from `PYMANAGER_PYTHON_DIRS`, which replace every default
+    /// interpreter location when set.
+    env_python_dirs: Option<Vec<PathBuf>>,
+    /// Ignore the discovery cache and scan again.
+    refresh: bool,
+    /// Seconds for which a discovery cache is trusted.
+    discovery_ttl: u64,
+    /// Number of automatic backups kept per log.
+    backup_count: usize,
+    /// Directories searched for virtualenvs.
+    venv_roots: Vec<PathBuf>,
+    /// Directory holding the logs and the discovery cache.
+    data_dir: PathBuf,
+    /// Read-only directory of system-wide logs layered under the user's,
+    /// when there is one.
+    system_dir: Option<PathBuf>,
+    /// Set by `--read-only`, or when the data directory is not writable,
+    /// as with logs shared by another user.
+    read_only: bool,
+    /// The backend chosen with `--backend` or in the config file.
+    backend: StorageBackend,
+    /// The layout chosen in the config file.
+    layout: LogLayout,
+    /// Where the logs are kept, following `backend` and `layout`.
+    store: Box<dyn LogStore>,
+    /// Held while a command may change the logs; see `lock_logs`.
+    log_lock: RefCell<Option<LogLock>>,
+    /// Binary name patterns from the config file and `--exclude`.
+    exclude_patterns: Vec<Regex>,
+    min_version: Option<PythonVersion>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct Project {
-    name: String,
-    created_at: u64,
-    last_accessed: u64,
+impl Context {
+    /// Writes `log` unless this is a dry run. Failing to write is fatal, as
+    /// the command's change would be lost.
+    fn save(&self, log: &ProjectLog) {
+        if let Err(err) = self.try_save(log) {
+            self.exit_with_error(&format!(
+                "could not write {}: {}",
+                log_path(self, &log.version).display(),
+                err
+            ));
+        }
+    }
+
+    /// Like `save`, but leaves a failure to the caller, for the table view
+    /// which has to restore the terminal first.
+    fn try_save(&self, log: &ProjectLog) -> io::Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+        let before = try_load_project_log(self, &log.version).ok();
+        save_project_log(self, log)?;
+        if let Some(before) = before {
+            journal_changes(self, &before, log);
+        }
+        Ok(())
+    }
+
+    /// Removes the log of `version`, journaling its projects as removed.
+    fn remove_log(&self, version: &str) -> io::Result<()> {
+        let before = try_load_project_log(self, version).ok();
+        self.store.remove(self, version)?;
+        if let Some(before) = before {
+            journal_changes(self, &before, &ProjectLog::empty(version));
+        }
+        Ok(())
+    }
+
+    /// Like `remove_log`, but keeps the contents aside under `label`.
+    fn set_aside_log(&self, version: &str, label: &str) -> io::Result<PathBuf> {
+        let before = try_load_project_log(self, version).ok();
+        let aside = self.store.set_aside(self, version, label)?;
+        if let Some(before) = before {
+            journal_changes(self, &before, &ProjectLog::empty(version));
+        }
+        Ok(aside)
+    }
+
+    /// Prints `message` as an error and exits, releasing the log lock first.
+    fn exit_with_error(&self, message: &str) -> ! {
+        eprintln!("Error: {}", message);
+        self.log_lock.take();
+        std::process::exit(1);
+    }
+
+    /// Prints "no matches" to stdout and exits with status 1, which scripts
+    /// check for rather than an error.
+    fn exit_no_matches(&self) -> ! {
+        println!("no matches");
+        self.log_lock.take();
+        std::process::exit(1);
+    }
+
+    /// Reports a log that cannot be read and is about to be skipped; with
+    /// `--strict` it is fatal instead.
+    fn skip_unreadable(&self, err: &LogError) {
+        if self.strict {
+            self.exit_with_error(&err.to_string());
+        }
+        eprintln!("warning: {} (skipped)", err);
+    }
+
+    /// Takes the lock that keeps concurrent invocations from overwriting
+    /// each other's changes. Anything that loads logs in order to change
+    /// them must hold it from the load until after the save; it is released
+    /// when the context goes away.
+    fn lock_logs(&self) -> Result<(), String> {
+        if self.dry_run || self.log_lock.borrow().is_some() {
+            return Ok(());
+        }
+        if self.read_only {
+            return Err(format!(
+                "data directory is read-only: {}",
+                self.data_dir.display()
+            ));
+        }
+        let lock = LogLock::acquire(&self.data_dir, LOCK_TIMEOUT)?;
+        *self.log_lock.borrow_mut() = Some(lock);
+        Ok(())
+    }
+
+    /// Releases the lock taken by `lock_logs` before the context goes away,
+    /// for the table view, which changes logs only now and then.
+    fn unlock_logs(&self) {
+        self.log_lock.take();
+    }
+
+    /// Prints the outcome of a mutation, prefixed with "would" on a dry run
+    /// so scripts can tell the two apart.
+    fn report(&self, message: &str) {
+        if self.dry_run {
+            println!("would: {}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    /// Asks for confirmation unless `--yes` was given. Dry runs never need
+    /// confirmation since they change nothing.
+    fn confirm(&self, prompt: &str) -> bool {
+        self.yes || self.dry_run || confirm(prompt)
+    }
+
+    /// Prints a note to stderr when `--verbose` was given.
+    fn debug(&self, message: &str) {
+        if self.verbose {
+            eprintln!("debug: {}", message);
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct ProjectLog {
-    version: String,
-    projects: Vec<Project>,
+/// How long to wait for another instance to release the log lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+const LOCK_FILE: &str = "pymanager.lock";
+
+/// An advisory lock on the data directory: a lock file created exclusively
+/// and removed on drop. It holds the owner's pid to help clean up after a
+/// crash.
+struct LogLock {
+    path: PathBuf,
 }
 
-fn get_python_versions() -> Vec<String> {
-    let mut versions = Vec::new();
-    let paths = vec!["/usr/bin", "/usr/local/bin"];
-
-    for path in paths {
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let file_name = entry.file_name();
-                    let file_name_str = file_name.to_string_lossy();
-
-                    if file_name_str.starts_with("python") {
-                        let re = Regex::new(r"python(\d+)\.(\d+)").unwrap();
-                        if let Some(caps) = re.captures(&file_name_str) {
-                            let version = format!("{}.{}", &caps[1], &caps[2]);
-                            if !versions.contains(&version) {
-                                //let version_clone = version.clone(); // Clonare la versione prima di spostarla nel vettore
-                                versions.push(version);
-                                //println!("Intercepted Python version: {}", version);
-                            }
-                        }
+impl LogLock {
+    fn acquire(data_dir: &Path, timeout: Duration) -> Result<Self, String> {
+        let path = data_dir.join(LOCK_FILE);
+        fs::create_dir_all(data_dir)
+            .map_err(|err| format!("could not create {}: {}", data_dir.display(), err))?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(LogLock { path });
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    if Instant::now() >= deadline {
+                        let owner = fs::read_to_string(&path).unwrap_or_default();
+                        return Err(format!(
+                            "another pymanager instance holds the lock {} (pid {}); \
+                             remove it if no other instance is running",
+                            path.display(),
+                            owner.trim()
+                        ));
                     }
+                    std::thread::sleep(Duration::from_millis(100));
                 }
+                Err(err) => return Err(format!("could not create {}: {}", path.display(), err)),
             }
         }
     }
-
-    versions
 }
 
+impl Drop for LogLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
 
-fn get_python_versions2() -> Vec<String> {
-    let mut versions = Vec::new();
-    let paths = vec!["/usr/bin", "/usr/local/bin"];
+/// Settings read from `~/.config/pymanager/config.toml`. Every setting has
+/// a default, so an empty file is a valid config.
+#[derive(Serialize, Deserialize)]
+struct Config {
+    /// Directory holding the logs, instead of the per-user data directory.
+    #[serde(default)]
+    data_dir: Option<PathBuf>,
+    /// Read-only directory of system-wide logs merged under the user's;
+    /// an empty path turns the layering off.
+    #[serde(default = "default_system_data_dir")]
+    system_data_dir: Option<PathBuf>,
+    /// Directories searched for interpreters in addition to `PATH`.
+    #[serde(default)]
+    extra_python_dirs: Vec<PathBuf>,
+    /// Seconds for which interpreter discovery results are reused.
+    #[serde(default = "default_discovery_ttl")]
+    discovery_cache_ttl: u64,
+    /// Directories holding virtualenvs, or projects with a `.venv`, in
+    /// addition to `~/.virtualenvs`.
+    #[serde(default)]
+    venv_roots: Vec<PathBuf>,
+    /// Regexes for interpreter binary names to leave out of discovery.
+    #[serde(default)]
+    exclude_patterns: Vec<String>,
+    /// Automatic backups kept per log; 0 turns them off.
+    #[serde(default = "default_backup_count")]
+    backup_count: usize,
+    /// Whether the logs are JSON files or a SQLite database.
+    #[serde(default)]
+    backend: StorageBackend,
+    /// With the JSON backend, whether each version's log is a file of its
+    /// own or a section of one shared document.
+    #[serde(default)]
+    layout: LogLayout,
+    /// Order of project listings when `--sort` is not given.
+    #[serde(default)]
+    default_sort: Option<SortKey>,
+    /// Colours of the table view.
+    #[serde(default)]
+    theme: TuiTheme,
+}
 
-    for path in paths {
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let file_name = entry.file_name();
-                    let file_name_str = file_name.to_str().unwrap_or("");
+/// Colours of the table view.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TuiTheme {
+    /// Yellow headers
+    #[default]
+    Default,
+    /// Blue headers, readable on a light background
+    Light,
+    /// No colours at all
+    Mono,
+}
 
-                    if file_name_str.starts_with("python") {
-                        let re = Regex::new(r"python\d+\.\d+").unwrap();
-                        if let Some(caps) = re.captures(file_name_str) {
-                            let version = caps.get(0).unwrap().as_str().to_string();
-                            if !versions.contains(&version) {
-                                versions.push(version);
-                            }
-                        }
-                    }
-                }
-            }
+impl TuiTheme {
+    fn header_style(self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match self {
+            TuiTheme::Default => style.fg(Color::Yellow),
+            TuiTheme::Light => style.fg(Color::Blue),
+            TuiTheme::Mono => style,
         }
     }
-    versions
 }
 
-fn load_project_log(version: &str) -> ProjectLog {
-    let path = format!("/var/log/pymanager/{}.json", version);
-    if Path::new(&path).exists() {
-        let data = fs::read_to_string(path).unwrap();
-        serde_json::from_str(&data).unwrap()
-    } else {
-        ProjectLog {
-            version: version.to_string(),
-            projects: vec![],
+/// How the logs are laid out in the data directory.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum LogLayout {
+    /// One `<version>.json` file per version
+    #[default]
+    PerVersion,
+    /// Every version in a single `pymanager.json`
+    Single,
+}
+
+impl LogLayout {
+    fn name(self) -> &'static str {
+        match self {
+            LogLayout::PerVersion => "per-version",
+            LogLayout::Single => "single",
+        }
+    }
+
+    fn store(self) -> Box<dyn LogStore> {
+        match self {
+            LogLayout::PerVersion => Box::new(JsonDirStore),
+            LogLayout::Single => Box::new(SingleFileStore),
         }
     }
 }
 
-fn save_project_log(log: &ProjectLog) {
-    let dir = "/var/log/pymanager";
-    fs::create_dir_all(dir).unwrap();
-    let path = format!("{}/{}.json", dir, log.version);
-    let data = serde_json::to_string(log).unwrap();
-    fs::write(path, data).unwrap();
+/// What the logs are stored in.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum StorageBackend {
+    /// JSON files, laid out as the `layout` setting says
+    #[default]
+    Json,
+    /// A single SQLite database, `pymanager.db`
+    Sqlite,
 }
 
-fn current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
+impl StorageBackend {
+    fn name(self) -> &'static str {
+        match self {
+            StorageBackend::Json => "json",
+            StorageBackend::Sqlite => "sqlite",
+        }
+    }
+
+    fn store(self, layout: LogLayout) -> Box<dyn LogStore> {
+        match self {
+            StorageBackend::Json => layout.store(),
+            StorageBackend::Sqlite => Box::new(SqliteStore),
+        }
+    }
 }
 
-fn list_python_versions() {
-    let versions = get_python_versions();
-    if versions.is_empty() {
-        println!("No Python versions found.");
-    } else {
-        println!("Python versions found:");
-        for version in versions {
-            println!("{}", version);
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_dir: None,
+            system_data_dir: default_system_data_dir(),
+            extra_python_dirs: Vec::new(),
+            discovery_cache_ttl: default_discovery_ttl(),
+            venv_roots: Vec::new(),
+            exclude_patterns: Vec::new(),
+            backup_count: default_backup_count(),
+            backend: StorageBackend::default(),
+            layout: LogLayout::default(),
+            default_sort: None,
+            theme: TuiTheme::default(),
         }
     }
 }
 
-fn list_python_projects(version: &str) {
-    let log = load_project_log(version);
-    if log.projects.is_empty() {
-        println!("No projects found for Python version {}", version);
+fn default_system_data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        None
     } else {
-        println!("Projects worked on by Python version {}:", version);
-        for project in log.projects {
-            println!(
-                "{} (created at {}, last accessed at {})",
-                project.name, project.created_at, project.last_accessed
-            );
-        }
+        Some(PathBuf::from("/usr/share/pymanager"))
     }
 }
 
-fn add_project(version: &str, project_name: &str) {
-    let mut log = load_project_log(version);
-    let timestamp = current_timestamp();
+fn default_discovery_ttl() -> u64 {
+    10 * 60
+}
 
-    if log.projects.iter().any(|p| p.name == project_name) {
-        println!(
-            "Project '{}' already exists for Python version {}",
-            project_name, version
+fn default_backup_count() -> usize {
+    5
+}
+
+fn config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("pymanager").join("config.toml"))
+}
+
+/// Whether the process `pid` is still running, when that can be told.
+fn process_alive(pid: u32) -> Option<bool> {
+    if cfg!(windows) {
+        return None;
+    }
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()
+        .map(|status| status.success())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Prints one line of the doctor's report, with a hint on how to fix a
+/// problem.
+fn print_check(status: CheckStatus, message: &str, hint: Option<&str>) {
+    let label = match status {
+        CheckStatus::Pass => "pass",
+        CheckStatus::Warn => "warn",
+        CheckStatus::Fail => "FAIL",
+    };
+    println!("{}  {}", label, message);
+    if let Some(hint) = hint.filter(|_| status != CheckStatus::Pass) {
+        println!("      hint: {}", hint);
+    }
+}
+
+fn doctor(ctx: &Context, fix: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failures = 0;
+    let mut check = |status: CheckStatus, message: &str, hint: Option<&str>| {
+        if status == CheckStatus::Fail {
+            failures += 1;
+        }
+        print_check(status, message, hint);
+    };
+
+    match load_config() {
+        Ok(_) => check(CheckStatus::Pass, "config file", None),
+        Err(err) => check(
+            CheckStatus::Fail,
+            &err.to_string(),
+            Some("correct the key with `pymanager config set` or edit the file"),
+        ),
+    }
+
+    let data_dir = &ctx.data_dir;
+    if !data_dir.exists() && fix && !ctx.dry_run {
+        fs::create_dir_all(data_dir)?;
+        println!("fix   created {}", data_dir.display());
+    }
+    if !data_dir.exists() {
+        check(
+            CheckStatus::Warn,
+            &format!("data directory {} does not exist yet", data_dir.display()),
+            Some("it is created on the first change, or now with `pymanager doctor --fix`"),
+        );
+    } else if !is_writable_dir(data_dir) {
+        check(
+            CheckStatus::Fail,
+            &format!("data directory {} is not writable", data_dir.display()),
+            Some(
+                "fix its permissions, or choose another one with --data-dir or PYMANAGER_DATA_DIR",
+            ),
         );
     } else {
-        log.projects.push(Project {
-            name: project_name.to_string(),
-            created_at: timestamp,
-            last_accessed: timestamp,
-        });
-        save_project_log(&log);
-        println!(
-            "Project '{}' added to Python version {}",
-            project_name, version
+        check(
+            CheckStatus::Pass,
+            &format!("data directory {} is writable", data_dir.display()),
+            None,
+        );
+    }
+
+    if let Some(system_dir) = &ctx.system_dir {
+        check(
+            CheckStatus::Pass,
+            &format!(
+                "system-wide logs in {} ({} version(s))",
+                system_dir.display(),
+                system_versions(ctx).len()
+            ),
+            None,
         );
     }
+
+    let versions = logged_versions(ctx);
+    let mut unreadable = 0;
+    for version in &versions {
+        if let Err(err) = try_load_project_log(ctx, version) {
+            unreadable += 1;
+            check(
+                CheckStatus::Fail,
+                &err.to_string(),
+                Some("fix the file by hand or bring back a backup with `pymanager restore`"),
+            );
+        }
+    }
+    if unreadable == 0 {
+        check(
+            CheckStatus::Pass,
+            &format!("{} log(s) parse", versions.len()),
+            None,
+        );
+    }
+
+    let installs = get_python_versions(ctx);
+    if installs.is_empty() {
+        check(
+            CheckStatus::Fail,
+            "no Python interpreters found",
+            Some("add their directories with --python-dir or extra_python_dirs in the config"),
+        );
+    } else {
+        check(
+            CheckStatus::Pass,
+            &format!("{} interpreter(s) found", installs.len()),
+            None,
+        );
+    }
+
+    let lock = data_dir.join(LOCK_FILE);
+    if lock.exists() {
+        let owner = fs::read_to_string(&lock).unwrap_or_default();
+        let pid = owner.trim().parse::<u32>().ok();
+        match pid.map(process_alive) {
+            Some(Some(true)) => check(
+                CheckStatus::Warn,
+                &format!(
+                    "lock file {} is held by running pid {}",
+                    lock.display(),
+                    owner.trim()
+                ),
+                Some("wait for that pymanager to finish"),
+            ),
+            Some(None) => check(
+                CheckStatus::Warn,
+                &format!(
+                    "lock file {} is held by pid {}",
+                    lock.display(),
+                    owner.trim()
+                ),
+                Some("remove it by hand if that process is gone"),
+            ),
+            _ if fix && !ctx.dry_run => {
+                fs::remove_file(&lock)?;
+                println!("fix   removed stale lock file {}", lock.display());
+            }
+            _ => check(
+                CheckStatus::Fail,
+                &format!("stale lock file {}", lock.display()),
+                Some("remove it with `pymanager doctor --fix`"),
+            ),
+        }
+    } else {
+        check(CheckStatus::Pass, "no lock held", None);
+    }
+
+    if failures > 0 {
+        return Err(format!("{} check(s) failed", failures).into());
+    }
+    Ok(())
 }
 
-fn show_table() -> Result<(), Box<dyn std::error::Error>> {
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+fn config_get(key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config()?;
+    let Some(key) = key else {
+        print!("{}", toml::to_string_pretty(&config)?);
+        return Ok(());
+    };
+    if !config_keys().iter().any(|known| known == key) {
+        return Err(format!(
+            "Unknown setting '{}' (known: {})",
+            key,
+            config_keys().join(", ")
+        )
+        .into());
+    }
+    // Settings without a value, such as an unset `data_dir`, print nothing.
+    match toml::Table::try_from(&config)?.get(key) {
+        Some(toml::Value::String(value)) => println!("{}", value),
+        Some(value) => println!("{}", value),
+        None => {}
+    }
+    Ok(())
+}
 
-    let versions = get_python_versions();
+/// Sets `key` in the config file, keeping the other settings as written.
+fn config_set(ctx: &Context, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path().ok_or("Neither XDG_CONFIG_HOME nor HOME is set")?;
+    if !config_keys().iter().any(|known| known == key) {
+        return Err(format!(
+            "Unknown setting '{}' (known: {})",
+            key,
+            config_keys().join(", ")
+        )
+        .into());
+    }
+    let mut settings = match fs::read_to_string(&path) {
+        Ok(data) => data.parse::<toml::Table>().map_err(|err| {
+            format!(
+                "{} is not valid TOML; fix or remove it first\n{}",
+                path.display(),
+                err
+            )
+        })?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => toml::Table::new(),
+        Err(err) => return Err(format!("Cannot read {}: {}", path.display(), err).into()),
+    };
+    let value = parse_config_value(value);
+    check_config_setting(key, &value).map_err(|err| format!("Invalid value for {}", err))?;
+    if key == "exclude_patterns" {
+        for pattern in value.as_array().into_iter().flatten() {
+            parse_exclude_pattern(pattern.as_str().unwrap_or_default())?;
+        }
+    }
+    let shown = value.to_string();
+    settings.insert(key.to_string(), value);
 
-    for version in &versions {
-        println!("Python version listed: {}", version);
+    if !ctx.dry_run {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomic(&path, toml::to_string_pretty(&settings)?.as_bytes())?;
     }
+    ctx.report(&format!("Set {} to {} in {}", key, shown, path.display()));
+    Ok(())
+}
 
-    let mut rows: Vec<Row> = Vec::new();
+/// Reads a value given on the command line as TOML, so that `10` is a
+/// number and `["a", "b"]` a list, or else as a plain string.
+fn parse_config_value(value: &str) -> toml::Value {
+    format!("value = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
 
-    for version in versions {
-        let log = load_project_log(&version);
-        for project in log.projects {
-            rows.push(Row::new(vec![
-                    Cell::from(version.clone()),
-                    Cell::from(project.name.clone()),
-                    Cell::from(format!("{}", project.created_at)),
-                    Cell::from(format!("{}", project.last_accessed)),
-            ]));
+fn parse_exclude_pattern(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| format!("invalid exclude pattern '{}': {}", pattern, err))
+}
+
+fn parse_min_version(text: &str) -> Result<PythonVersion, String> {
+    PythonVersion::parse(text)
+        .filter(|version| version.to_string() == text)
+        .ok_or_else(|| format!("expected a version like 3.8, got '{}'", text))
+}
+
+/// Loads the config file, falling back to the defaults when there is none.
+fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
+    match config_path() {
+        Some(path) if path.exists() => {
+            let data = fs::read_to_string(&path)?;
+            parse_config(&data)
+                .map_err(|err| format!("Invalid config file {}: {}", path.display(), err).into())
         }
+        _ => Ok(Config::default()),
+    }
+}
+
+/// The names of every setting.
+fn config_keys() -> Vec<String> {
+    match serde_json::to_value(Config::default()) {
+        Ok(serde_json::Value::Object(settings)) => settings.keys().cloned().collect(),
+        _ => Vec::new(),
     }
+}
 
-    loop {
-        terminal.draw(|f| {
-            let size = f.size();
-            let block = Block::default().borders(Borders::ALL).title("Python Projects");
-            let table = Table::new(rows.clone())
-                .block(block)
-                .header(Row::new(vec![
-                        Cell::from("Version").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-                        Cell::from("Project").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-                        Cell::from("Created At").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-                        Cell::from("Last Accessed").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-                ]))
-                .widths(&[
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
-                ]);
-            f.render_widget(table, size);
-        })?;
+/// Checks a single setting, so that an error names the key it is about.
+fn check_config_setting(key: &str, value: &toml::Value) -> Result<(), String> {
+    let mut single = toml::Table::new();
+    single.insert(key.to_string(), value.clone());
+    toml::Value::Table(single)
+        .try_into::<Config>()
+        .map(|_| ())
+        .map_err(|err| format!("key '{}': {}", key, err.message()))
+}
 
-        if let Event::Key(key) = event::read()? {
-            if key.code == KeyCode::Char('q') {
-                break;
-            }
+fn parse_config(data: &str) -> Result<Config, String> {
+    let settings: toml::Table = data
+        .parse()
+        .map_err(|err: toml::de::Error| err.to_string())?;
+    let known = config_keys();
+    for (key, setting) in &settings {
+        if !known.contains(key) {
+            eprintln!("warning: config: ignoring unknown key '{}'", key);
+            continue;
         }
+        check_config_setting(key, setting)?;
     }
+    toml::Value::Table(settings)
+        .try_into()
+        .map_err(|err: toml::de::Error| err.to_string())
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
+#[derive(Subcommand)]
+enum Commands {
+    /// List all Python versions available on the system
+    ListPythonVersions {
+        #[command(flatten)]
+        options: VersionListOptions,
+    },
+    /// List conda environments and virtualenvs with their Python version
+    ListEnvs,
+    /// Check that the interpreter of a Python version has pip
+    CheckPip { version: String },
+    /// Check that the interpreter of a Python version runs and has the
+    /// commonly missing standard modules and pip
+    Check {
+        version: String,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show which interpreters `python3` and `python` resolve to
+    DefaultPython,
+    /// List all projects worked on by a specific Python version
+    ListPythonProjects {
+        version: String,
+        #[command(flatten)]
+        filters: ListFilters,
+        /// Mark each project as coming from the user or the system-wide log
+        #[arg(long)]
+        origin: bool,
+    },
+    /// List the projects of every logged Python version
+    ListAllProjects {
+        #[command(flatten)]
+        filters: ListFilters,
+    },
+    /// Add a project to the log for a specific Python version
+    AddProject {
+        #[arg(required_unless_present = "interactive")]
+        version: Option<String>,
+        #[arg(required_unless_present_any = ["from_file", "interactive"])]
+        project: Option<String>,
+        /// One-line description of the project
+        #[arg(long)]
+        description: Option<String>,
+        /// Directory where the project lives
+        #[arg(long)]
+        path: Option<PathBuf>,
+        /// URL of the project's git remote
+        #[arg(long = "repo", value_name = "URL")]
+        repo_url: Option<String>,
+        /// Read the origin URL from the project's git config
+        #[arg(long, requires = "path", conflicts_with = "repo_url")]
+        detect_repo: bool,
+        /// Store the output of `pip freeze` for the project's interpreter
+        #[arg(long)]
+        snapshot_requirements: bool,
+        /// Interpreter binary the project uses, when several match the version
+        #[arg(long, value_name = "PATH")]
+        interpreter: Option<PathBuf>,
+        /// Read project names from a file, one per line (`-` for stdin)
+        #[arg(
+            long,
+            conflicts_with_all = ["project", "description", "path", "repo_url", "snapshot_requirements", "interpreter"]
+        )]
+        from_file: Option<PathBuf>,
+        /// Choose the version and enter the project details at prompts
+        #[arg(long, conflicts_with_all = ["version", "project", "from_file"])]
+        interactive: bool,
+    },
+    /// Remove a project from the log for a specific Python version
+    RemoveProject {
+        #[arg(required_unless_present = "all_versions")]
+        version: Option<String>,
+        #[arg(required_unless_present = "pattern")]
+        project: Option<String>,
+        /// Remove every project whose name matches this regular expression
+        #[arg(long = "match", value_name = "REGEX", conflicts_with = "project")]
+        pattern: Option<String>,
+        /// With --match, sweep the log of every logged Python version
+        #[arg(long, requires = "pattern", conflicts_with = "version")]
+        all_versions: bool,
+    },
+    /// Rename a project, keeping its creation time
+    RenameProject {
+        version: String,
+        old_name: String,
+        new_name: String,
+    },
+    /// Update the last accessed time of one or more projects
+    Touch {
+        version: String,
+        #[arg(required = true)]
+        projects: Vec<String>,
+    },
+    /// Move a project from one Python version to another
+    MoveProject {
+        from_version: String,
+        to_version: String,
+        project: String,
+        /// Replace a project with the same name in the destination
+        #[arg(long)]
+        force: bool,
+    },
+    /// Copy a project entry to another Python version
+    CopyProject {
+        from_version: String,
+        to_version: String,
+        project: String,
+        /// Replace a project with the same name in the destination
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Set the description of a project
+    SetDescription {
+        version: String,
+        project: String,
+        text: String,
+    },
+    /// Set the directory where a project lives
+    SetPath {
+        version: String,
+        project: String,
+        path: PathBuf,
+    },
+    /// Set the git remote URL of a project
+    SetRepo {
+        version: String,
+        project: String,
+        #[arg(required_unless_present = "detect")]
+        url: Option<String>,
+        /// Read the origin URL from the git config in the project path
+        #[arg(long, conflicts_with = "url")]
+        detect: bool,
+    },
+    /// Open a shell (or run a command) in the project directory
+    Open {
+        version: String,
+        project: String,
+        /// Command to run instead of $SHELL
+        #[arg(long)]
+        exec: Option<String>,
+    },
+    /// Search project names across every logged Python version
+    Search {
+        pattern: String,
+        /// Treat the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+    },
+    /// Show the Python versions a project is registered under
+    Which {
+        project: String,
+        /// Match any project whose name contains the given text
+        #[arg(long)]
+        fuzzy: bool,
+    },
+    /// Show which projects depend on the interpreter of a Python version
+    Impact {
+        #[arg(required_unless_present = "all")]
+        version: Option<String>,
+        /// Compare every discovered interpreter against every log instead
+        #[arg(long, conflicts_with = "version")]
+        all: bool,
+    },
+    /// Remove projects that have not been accessed for a number of days
+    Prune {
+        #[arg(required_unless_present = "all_versions")]
+        version: Option<String>,
+        /// Remove projects last accessed more than this many days ago
+        #[arg(long)]
+        older_than: u64,
+        /// Prune the log of every logged Python version
+        #[arg(long, conflicts_with = "version")]
+        all_versions: bool,
+    },
+    /// Hide a project from listings without deleting it
+    Archive { version: String, project: String },
+    /// Restore an archived project
+    Unarchive { version: String, project: String },
+    /// Append a note to a project
+    Note {
+        version: String,
+        project: String,
+        text: String,
+    },
+    /// List the notes of a project
+    Notes { version: String, project: String },
+    /// Summarize the project logs
+    Stats {
+        /// Print the summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Find project names logged under more than one Python version
+    Duplicates {
+        /// Compare names exactly (case-sensitive)
+        #[arg(long, conflicts_with = "normalized")]
+        exact: bool,
+        /// Compare names ignoring case and `-`/`_`/`.` separators (default)
+        #[arg(long)]
+        normalized: bool,
+    },
+    /// Find Python projects under a directory and register them
+    Scan {
+        dir: PathBuf,
+        /// Version to use for projects whose version cannot be inferred
+        #[arg(long)]
+        default_version: Option<String>,
+    },
+    /// Export every project log into a single JSON document
+    Export {
+        /// File to write instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Merge a document produced by `export` into the local logs
+    Import { file: PathBuf },
+    /// Show every detail of a single project
+    Show {
+        version: String,
+        project: String,
+        /// Print the project as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove every project logged for a Python version
+    Clear {
+        version: String,
+        /// Delete the log file instead of emptying it
+        #[arg(long)]
+        delete_file: bool,
+        /// Copy the log to <version>.json.bak before clearing it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Rewrite every log in the canonical formatting without changing its content
+    FormatLogs,
+    /// Remove empty logs, and with --orphans list logs of versions no longer installed
+    Compact {
+        /// Also report non-empty logs whose version matches no discovered
+        /// interpreter; they are only removed when --yes is given as well
+        #[arg(long)]
+        orphans: bool,
+    },
+    /// Move every log between the per-version and single-file layouts
+    ConvertLayout {
+        #[arg(value_enum)]
+        to: LogLayout,
+    },
+    /// Move every log between the JSON and SQLite backends
+    MigrateStorage {
+        #[arg(value_enum)]
+        to: StorageBackend,
+    },
+    /// List the automatic backups of a version's log, or restore one
+    Restore {
+        version: String,
+        /// List the available backups instead of restoring one
+        #[arg(long, conflicts_with = "backup")]
+        list: bool,
+        /// Timestamp of the backup to restore (defaults to the newest)
+        #[arg(long)]
+        backup: Option<String>,
+    },
+    /// Show the journal of changes made to the logs
+    Journal {
+        /// Number of entries to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Only show changes to this project
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// Show the most recently accessed projects across all versions
+    Recent {
+        /// Number of projects to show
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+        /// Only consider projects of this Python version
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Pin a project so it is listed first
+    Pin { version: String, project: String },
+    /// Unpin a project
+    Unpin { version: String, project: String },
+    /// Fold the projects of one version's log into another
+    MergeVersion { from: String, into: String },
+    /// Rename a version's log when the target does not exist yet
+    RenameVersion { from: String, to: String },
+    /// Manage short aliases for project names
+    Alias {
+        #[command(subcommand)]
+        action: AliasAction,
+    },
+    /// Manage arbitrary key/value metadata on a project
+    Meta {
+        #[command(subcommand)]
+        action: MetaAction,
+    },
+    /// Show when a project was accessed
+    History { version: String, project: String },
+    /// Manage project tags
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Report the disk usage of projects with a stored path
+    Size {
+        /// Only consider projects of this Python version
+        #[arg(long)]
+        version: Option<String>,
+        /// Store the computed sizes in the logs so the table can show them
+        #[arg(long)]
+        save: bool,
+    },
+    /// Show the site-packages directories of a Python version's interpreter
+    SitePackages {
+        version: String,
+        /// Also show how much disk space each directory takes
+        #[arg(long)]
+        size: bool,
+    },
+    /// Create a project directory with a virtualenv and register it
+    Init {
+        version: String,
+        project: String,
+        /// Directory to create (defaults to <project> in the current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Store the installed packages of a project, or compare against the stored ones
+    #[command(subcommand_negates_reqs = true)]
+    Snapshot {
+        #[command(subcommand)]
+        action: Option<SnapshotAction>,
+        #[arg(required = true)]
+        version: Option<String>,
+        #[arg(required = true)]
+        project: Option<String>,
+    },
+    /// Check every log for dangling paths, unknown versions and duplicate names
+    Validate {
+        /// Offer to clear project paths that no longer exist and to merge
+        /// duplicate names
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Merge projects logged more than once under the same name
+    Dedupe { version: String },
+    /// Record checksums of the log files, and report which changed since the last run
+    Integrity {
+        /// Only compare against the recorded checksums, failing on any
+        /// difference, without recording new ones
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Check the config, data directory, logs, interpreters and lock file
+    Doctor {
+        /// Create a missing data directory and remove a stale lock file
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Show or change settings in the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Show projects in a table
+    ShowTable {
+        /// Column to sort the table by; keys 1-4 change it in the table
+        #[arg(long, value_enum)]
+        sort: Option<TableSort>,
+        /// Sort in descending order
+        #[arg(long, requires = "sort")]
+        reverse: bool,
+    },
+}
 
-    Ok(())
+impl Commands {
+    /// Whether the command may change a log, and so must hold the log lock
+    /// for its whole run. `open` only takes it once its shell has exited.
+    fn changes_logs(&self) -> bool {
+        !matches!(
+            self,
+            Commands::ListPythonVersions { .. }
+                | Commands::ListEnvs
+                | Commands::CheckPip { .. }
+                | Commands::Check { .. }
+                | Commands::DefaultPython
+                | Commands::ListPythonProjects { .. }
+                | Commands::ListAllProjects { .. }
+                | Commands::Search { .. }
+                | Commands::Which { .. }
+                | Commands::Open { .. }
+                | Commands::Impact { .. }
+                | Commands::Notes { .. }
+                | Commands::Stats { .. }
+                | Commands::Duplicates { .. }
+                | Commands::Export { .. }
+                | Commands::Show { .. }
+                | Commands::Recent { .. }
+                | Commands::Journal { .. }
+                | Commands::Restore { list: true, .. }
+                | Commands::History { .. }
+                | Commands::SitePackages { .. }
+                | Commands::Size { save: false, .. }
+                | Commands::Snapshot {
+                    action: Some(SnapshotAction::Diff { .. }),
+                    ..
+                }
+                | Commands::Validate { fix: false }
+                | Commands::Config { .. }
+                | Commands::Integrity { verify: true }
+                | Commands::Doctor { .. }
+                | Commands::ShowTable { .. }
+        )
+    }
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Args)]
+struct VersionListOptions {
+    /// Show how many projects are logged for each version
+    #[arg(long)]
+    with_counts: bool,
+    /// Show where each interpreter lives and which names link to it
+    #[arg(long, visible_alias = "long")]
+    paths: bool,
+    /// With --paths, show whether each interpreter can run pip
+    #[arg(long, requires = "paths")]
+    pip: bool,
+    /// Run each interpreter to read its full version
+    #[arg(long)]
+    probe: bool,
+    /// How many interpreters to probe at once
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..=16), requires = "probe")]
+    jobs: u64,
+    /// Also list the interpreters of virtualenvs in the venv roots
+    #[arg(long)]
+    include_venvs: bool,
+    /// Hide CPython versions past their end-of-life
+    #[arg(long)]
+    only_supported: bool,
+    /// Print the interpreters as a JSON array
+    #[arg(long, conflicts_with_all = ["with_counts", "paths"])]
+    json: bool,
+}
+
+#[derive(Args)]
+struct ListFilters {
+    /// Only list projects carrying this tag
+    #[arg(long)]
+    tag: Option<String>,
+    /// Also list archived projects
+    #[arg(long)]
+    include_archived: bool,
+    /// Sort the listing instead of showing it in stored order; defaults to
+    /// `default_sort` from the config file
+    #[arg(long, value_enum)]
+    sort: Option<SortKey>,
+    /// Reverse the order of the listing
+    #[arg(long)]
+    reverse: bool,
+    /// Only list pinned projects
+    #[arg(long)]
+    favorites: bool,
+    /// Only list projects accessed at or after this time
+    #[arg(long, value_parser = parse_time_spec)]
+    since: Option<u64>,
+    /// Only list projects accessed before this time
+    #[arg(long, value_parser = parse_time_spec)]
+    before: Option<u64>,
+    /// Show times as how long ago they were, such as "3 days ago"
+    #[arg(long)]
+    relative: bool,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    Name,
+    Created,
+    Accessed,
+}
+
+impl ListFilters {
+    /// Selects and orders the projects to list. The log itself is left
+    /// untouched so the order on disk never changes.
+    fn apply<'a>(&self, ctx: &Context, projects: &'a [Project]) -> Vec<&'a Project> {
+        let tag = self.tag.as_deref().map(normalize_tag);
+        let mut selected: Vec<&Project> = projects
+            .iter()
+            .filter(|p| self.include_archived || !p.status.is_archived())
+            .filter(|p| !self.favorites || p.favorite)
+            .filter(|p| tag.as_ref().is_none_or(|t| p.tags.contains(t)))
+            .filter(|p| self.since.is_none_or(|since| p.last_accessed >= since))
+            .filter(|p| self.before.is_none_or(|before| p.last_accessed < before))
+            .collect();
 
-    match &cli.command {
-        Commands::ListPythonVersions => {
-            list_python_versions();
+        if let Some(key) = self.sort.or(ctx.default_sort) {
+            selected.sort_by(|a, b| {
+                let primary = match key {
+                    SortKey::Name => std::cmp::Ordering::Equal,
+                    SortKey::Created => a.created_at.cmp(&b.created_at),
+                    SortKey::Accessed => a.last_accessed.cmp(&b.last_accessed),
+                };
+                primary.then_with(|| a.name.cmp(&b.name))
+            });
         }
-        Commands::ListPythonProjects { version } => {
-            list_python_projects(version);
+        if self.reverse {
+            selected.reverse();
+        }
+        // Pinned projects always come first; the sort is stable so the
+        // order chosen above is kept within each group.
+        selected.sort_by_key(|p| !p.favorite);
+        selected
+    }
+}
+
+/// Columns the table can be sorted by, in the order of their keys.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TableSort {
+    Version,
+    Name,
+    Created,
+    Accessed,
+}
+
+impl TableSort {
+    fn from_key(key: char) -> Option<Self> {
+        match key {
+            '1' => Some(TableSort::Version),
+            '2' => Some(TableSort::Name),
+            '3' => Some(TableSort::Created),
+            '4' => Some(TableSort::Accessed),
+            _ => None,
         }
-        Commands::AddProject { version, project } => {
-            add_project(version, project);
+    }
+
+    /// Orders two table entries, falling back to version and name so that
+    /// the order is stable between redraws.
+    fn compare(self, a: &(String, Project), b: &(String, Project)) -> std::cmp::Ordering {
+        let by_version = || version_sort_key(&a.0).cmp(&version_sort_key(&b.0));
+        let by_name = || a.1.name.cmp(&b.1.name);
+        match self {
+            TableSort::Version => by_version().then_with(by_name),
+            TableSort::Name => by_name().then_with(by_version),
+            TableSort::Created => a.1.created_at.cmp(&b.1.created_at),
+            TableSort::Accessed => a.1.last_accessed.cmp(&b.1.last_accessed),
         }
-        Commands::ShowTable => {
-            show_table().unwrap();
+        .then_with(by_version)
+        .then_with(by_name)
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a setting, or every setting when no key is given
+    Get { key: Option<String> },
+    /// Change a setting; the value is read as TOML, or else as a string
+    Set { key: String, value: String },
+    /// Print where the config file is
+    Path,
+}
+
+#[derive(Subcommand)]
+enum AliasAction {
+    /// Add an alias to a project
+    Add {
+        version: String,
+        project: String,
+        alias: String,
+    },
+    /// Remove an alias from a project
+    Remove {
+        version: String,
+        project: String,
+        alias: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetaAction {
+    /// Set a metadata value
+    Set {
+        version: String,
+        project: String,
+        key: String,
+        value: String,
+    },
+    /// Print a metadata value
+    Get {
+        version: String,
+        project: String,
+        key: String,
+    },
+    /// Remove a metadata value
+    Unset {
+        version: String,
+        project: String,
+        key: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    /// Add a tag to a project
+    Add {
+        version: String,
+        project: String,
+        tag: String,
+    },
+    /// Remove a tag from a project
+    Remove {
+        version: String,
+        project: String,
+        tag: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Compare the installed packages with the stored snapshot
+    Diff { version: String, project: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct Project {
+    name: String,
+    created_at: u64,
+    last_accessed: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repo_url: Option<String>,
+    /// The interpreter binary the project was added with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interpreter_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "ProjectStatus::is_active")]
+    status: ProjectStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archived_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<Note>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    favorite: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    access_log: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size_computed_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requirements_snapshot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requirements_snapshot_at: Option<u64>,
+    /// Fields this version does not know, such as those written by a newer
+    /// pymanager, kept so that saving does not drop them.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Project {
+    fn new(name: &str, timestamp: u64) -> Self {
+        Project {
+            name: name.to_string(),
+            created_at: timestamp,
+            last_accessed: timestamp,
+            description: None,
+            tags: Vec::new(),
+            path: None,
+            repo_url: None,
+            interpreter_path: None,
+            status: ProjectStatus::Active,
+            archived_at: None,
+            notes: Vec::new(),
+            favorite: false,
+            aliases: Vec::new(),
+            metadata: BTreeMap::new(),
+            access_log: Vec::new(),
+            size_bytes: None,
+            size_computed_at: None,
+            requirements_snapshot: None,
+            requirements_snapshot_at: None,
+            extra: serde_json::Map::new(),
         }
     }
 }
 
+/// Number of access timestamps kept per project.
+const ACCESS_LOG_LIMIT: usize = 50;
+
+/// Marks `project` as accessed at `timestamp`, keeping only the most recent
+/// `ACCESS_LOG_LIMIT` entries of its access history.
+fn record_access(project: &mut Project, timestamp: u64) {
+    project.last_accessed = timestamp;
+    project.access_log.push(timestamp);
+    trim_access_log(&mut project.access_log);
+}
+
+fn trim_access_log(access_log: &mut Vec<u64>) {
+    if access_log.len() > ACCESS_LOG_LIMIT {
+        access_log.drain(..access_log.len() - ACCESS_LOG_LIMIT);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct Note {
+    text: String,
+    created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum ProjectStatus {
+    #[default]
+    Active,
+    Archived,
+}
+
+impl ProjectStatus {
+    fn is_active(&self) -> bool {
+        *self == ProjectStatus::Active
+    }
+
+    fn is_archived(&self) -> bool {
+        *self == ProjectStatus::Archived
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(try_from = "StoredProjectLog")]
+struct ProjectLog {
+    /// Always `LOG_SCHEMA_VERSION` once loaded, as older logs are migrated.
+    schema_version: u32,
+    version: String,
+    projects: Vec<Project>,
+    /// Fields this version does not know, kept as they are.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ProjectLog {
+    fn empty(version: &str) -> Self {
+        ProjectLog {
+            schema_version: LOG_SCHEMA_VERSION,
+            version: version.to_string(),
+            projects: Vec::new(),
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// Schema of the logs this version writes. Logs from before the field
+/// existed are schema 1.
+const LOG_SCHEMA_VERSION: u32 = 2;
+
+/// `LOG_MIGRATIONS[n]` upgrades a schema `n + 1` log to schema `n + 2`.
+const LOG_MIGRATIONS: [fn(&mut ProjectLog); 1] = [migrate_log_v1_to_v2];
+
+/// Projects logged before the access history was kept have none; seed it
+/// with the last access so `history` has something to show.
+fn migrate_log_v1_to_v2(log: &mut ProjectLog) {
+    for project in &mut log.projects {
+        if project.access_log.is_empty() {
+            project.access_log.push(project.last_accessed);
+        }
+    }
+}
+
+/// A log as stored, before it is migrated. Fields that are renamed or
+/// dropped later keep being accepted here so that old logs still parse.
+#[derive(Deserialize)]
+struct StoredProjectLog {
+    #[serde(default = "legacy_log_schema")]
+    schema_version: u32,
+    version: String,
+    projects: Vec<Project>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn legacy_log_schema() -> u32 {
+    1
+}
+
+impl TryFrom<StoredProjectLog> for ProjectLog {
+    type Error = String;
+
+    /// Brings the log up to `LOG_SCHEMA_VERSION`, one step at a time.
+    fn try_from(stored: StoredProjectLog) -> Result<Self, String> {
+        if stored.schema_version > LOG_SCHEMA_VERSION {
+            return Err(format!(
+                "created by a newer pymanager (log schema {}, this version reads up to {}); upgrade pymanager to use it",
+                stored.schema_version, LOG_SCHEMA_VERSION
+            ));
+        }
+        if stored.schema_version == 0 {
+            return Err("invalid schema_version 0".to_string());
+        }
+        let mut log = ProjectLog {
+            schema_version: LOG_SCHEMA_VERSION,
+            version: stored.version,
+            projects: stored.projects,
+            extra: stored.extra,
+        };
+        for migration in &LOG_MIGRATIONS[stored.schema_version as usize - 1..] {
+            migration(&mut log);
+        }
+        Ok(log)
+    }
+}
+
+/// Classic interpreter locations, searched after `PATH` even when they are
+/// not on it.
+const PYTHON_BIN_DIRS: [&str; 2] = ["/usr/bin", "/usr/local/bin"];
+
+/// Where Homebrew and the python.org framework installer put interpreters
+/// on macOS. These are often not on `PATH`: Homebrew only links the newest
+/// `python@3.x`, and framework builds are reached through `/usr/local/bin`
+/// links that are easy to remove.
+fn macos_python_dirs() -> Vec<PathBuf> {
+    macos_python_dirs_in(Path::new("/"))
+}
+
+/// The directories of `macos_python_dirs` under `root` rather than `/`.
+fn macos_python_dirs_in(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![root.join("opt/homebrew/bin")];
+    for opt in ["opt/homebrew/opt", "usr/local/opt"] {
+        if let Ok(entries) = fs::read_dir(root.join(opt)) {
+            dirs.extend(
+                entries
+                    .flatten()
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with("python@"))
+                    .map(|entry| entry.path().join("bin")),
+            );
+        }
+    }
+    if let Ok(entries) = fs::read_dir(root.join("Library/Frameworks/Python.framework/Versions")) {
+        dirs.extend(
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name() != "Current")
+                .map(|entry| entry.path().join("bin")),
+        );
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs.sort();
+    dirs
+}
+
+/// Every directory on `PATH` followed by the classic and platform ones.
+fn default_python_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    if !cfg!(windows) {
+        dirs.extend(PYTHON_BIN_DIRS.iter().map(PathBuf::from));
+    }
+    if cfg!(target_os = "macos") {
+        dirs.extend(macos_python_dirs());
+    }
+    dirs
+}
+
+/// The default search directories, or those from `PYMANAGER_PYTHON_DIRS`,
+/// followed by the configured ones, in order and without duplicates.
+fn python_search_dirs(ctx: &Context) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match &ctx.env_python_dirs {
+        Some(env_dirs) => env_dirs.clone(),
+        None => default_python_dirs(),
+    };
+    for dir in &ctx.python_dirs {
+        if dir.is_dir() {
+            dirs.push(dir.clone());
+        } else {
+            ctx.debug(&format!(
+                "ignoring interpreter directory {}: not found",
+                dir.display()
+            ));
+        }
+    }
+    let mut seen = BTreeSet::new();
+    dirs.retain(|dir| !dir.as_os_str().is_empty() && seen.insert(dir.clone()));
+    dirs
+}
+
+/// Where an interpreter was discovered.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum InstallSource {
+    Path,
+    Pyenv,
+    Asdf,
+    Conda,
+    Venv,
+    Uv,
+    Snap,
+    Flatpak,
+    Nix,
+    #[cfg(windows)]
+    PyLauncher,
+}
+
+/// A parsed `X.Y` or `X.Y.Z` Python version. Compares numerically, so 3.9
+/// sorts before 3.10, and `X.Y` before any `X.Y.Z`. Serialized as its
+/// `X.Y[.Z]` text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(into = "String", try_from = "String")]
+struct PythonVersion {
+    major: u32,
+    minor: u32,
+    patch: Option<u32>,
+}
+
+impl PythonVersion {
+    /// Finds the first `X.Y` or `X.Y.Z` in `text`.
+    fn parse(text: &str) -> Option<Self> {
+        let re = Regex::new(r"(\d+)\.(\d+)(?:\.(\d+))?").unwrap();
+        let caps = re.captures(text)?;
+        Some(PythonVersion {
+            major: caps[1].parse().ok()?,
+            minor: caps[2].parse().ok()?,
+            patch: caps.get(3).and_then(|patch| patch.as_str().parse().ok()),
+        })
+    }
+
+    /// The `X.Y` part of the version.
+    fn minor_only(self) -> Self {
+        PythonVersion {
+            patch: None,
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.patch {
+            Some(patch) => write!(f, "{}.{}.{}", self.major, self.minor, patch),
+            None => write!(f, "{}.{}", self.major, self.minor),
+        }
+    }
+}
+
+impl From<PythonVersion> for String {
+    fn from(version: PythonVersion) -> Self {
+        version.to_string()
+    }
+}
+
+impl TryFrom<String> for PythonVersion {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        PythonVersion::parse(&text).ok_or_else(|| format!("invalid Python version '{}'", text))
+    }
+}
+
+/// End-of-life dates of the CPython release lines, from the Python
+/// developer guide. Dates for lines still supported are the planned ones.
+const PYTHON_EOL_DATES: [(&str, &str); 11] = [
+    ("2.7", "2020-01-01"),
+    ("3.6", "2021-12-23"),
+    ("3.7", "2023-06-27"),
+    ("3.8", "2024-10-07"),
+    ("3.9", "2025-10-31"),
+    ("3.10", "2026-10-31"),
+    ("3.11", "2027-10-31"),
+    ("3.12", "2028-10-31"),
+    ("3.13", "2029-10-31"),
+    ("3.14", "2030-10-31"),
+    ("3.15", "2031-10-31"),
+];
+
+/// Lines reaching end-of-life within this many months are flagged too.
+const EOL_WARNING_MONTHS: i32 = 12;
+
+/// When the CPython line of `version` reaches end-of-life, if known.
+fn eol_date(version: PythonVersion) -> Option<NaiveDate> {
+    let minor = version.minor_only().to_string();
+    PYTHON_EOL_DATES
+        .iter()
+        .find(|(line, _)| *line == minor)
+        .and_then(|(_, date)| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+/// "EOL" or "EOL in N months" for CPython lines that are, or soon will be,
+/// out of support.
+fn eol_status(version: PythonVersion, today: NaiveDate) -> Option<String> {
+    let eol = eol_date(version)?;
+    if eol <= today {
+        return Some("EOL".to_string());
+    }
+    let mut months = (eol.year() - today.year()) * 12 + eol.month() as i32 - today.month() as i32;
+    if eol.day() < today.day() {
+        months -= 1;
+    }
+    match months {
+        0 => Some("EOL in under a month".to_string()),
+        1 => Some("EOL in 1 month".to_string()),
+        months if months < EOL_WARNING_MONTHS => Some(format!("EOL in {} months", months)),
+        _ => None,
+    }
+}
+
+/// Whether the log named `version` belongs to a CPython line past its
+/// end-of-life. Logs of other implementations are never considered EOL.
+fn is_eol_log(version: &str, today: NaiveDate) -> bool {
+    version.starts_with(|c: char| c.is_ascii_digit())
+        && PythonVersion::parse(version)
+            .and_then(eol_date)
+            .is_some_and(|eol| eol <= today)
+}
+
+/// Sort key for log names: grouped by any implementation prefix such as
+/// `pypy`, then ordered by version, with unparsable names last.
+fn version_sort_key(name: &str) -> (String, Option<PythonVersion>, String) {
+    let prefix: String = name.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let version = PythonVersion::parse(&name[prefix.len()..]);
+    (prefix, version, name.to_string())
+}
+
+/// The Python implementation behind an interpreter.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum Implementation {
+    CPython,
+    PyPy,
+}
+
+/// An interpreter found by `get_python_versions`.
+#[derive(Serialize, Deserialize)]
+struct PythonInstall {
+    /// The `X.Y` language version.
+    version: PythonVersion,
+    implementation: Implementation,
+    /// The full `X.Y.Z` version, when the source records it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    full_version: Option<PythonVersion>,
+    /// The resolved binary, with symlinks followed.
+    path: PathBuf,
+    /// Other names found for the same binary, such as a `python3` symlink.
+    aliases: Vec<PathBuf>,
+    source: InstallSource,
+    /// Name of the conda environment the interpreter belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env_name: Option<String>,
+    /// What the interpreter reported about its build, when probed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    build: Option<BuildInfo>,
+}
+
+/// The machine an interpreter was built for and the compiler that built
+/// it, or "unknown" for either when the interpreter could not say.
+#[derive(Serialize, Deserialize, Clone)]
+struct BuildInfo {
+    architecture: String,
+    compiler: String,
+}
+
+impl BuildInfo {
+    fn unknown() -> Self {
+        BuildInfo {
+            architecture: "unknown".to_string(),
+            compiler: "unknown".to_string(),
+        }
+    }
+}
+
+impl PythonInstall {
+    /// Name of the log this interpreter's projects are kept in. PyPy logs
+    /// are prefixed so they do not collide with CPython ones of the same
+    /// language version; move projects that were logged under the plain
+    /// version with `merge-version X.Y pypyX.Y`.
+    fn log_key(&self) -> String {
+        match self.implementation {
+            Implementation::CPython => self.version.to_string(),
+            Implementation::PyPy => format!("pypy{}", self.version),
+        }
+    }
+
+    /// Short note on where the interpreter comes from; plain `PATH` entries
+    /// have none.
+    fn source_tag(&self) -> Option<String> {
+        let full_version = self.full_version.unwrap_or(self.version).to_string();
+        match self.source {
+            InstallSource::Path => None,
+            InstallSource::Pyenv => Some(format!("pyenv {}", full_version)),
+            InstallSource::Asdf => Some(format!("asdf {}", full_version)),
+            InstallSource::Conda => Some(format!(
+                "conda {}",
+                self.env_name.as_deref().unwrap_or(&full_version)
+            )),
+            InstallSource::Uv => Some(format!("uv {}", full_version)),
+            InstallSource::Snap => Some(format!("snap {}", full_version)),
+            InstallSource::Nix => Some(format!("nix {}", full_version)),
+            InstallSource::Flatpak => Some(format!(
+                "flatpak {}",
+                self.env_name.as_deref().unwrap_or(&full_version)
+            )),
+            InstallSource::Venv => Some(format!(
+                "venv {}",
+                self.env_name.as_deref().unwrap_or(&full_version)
+            )),
+            #[cfg(windows)]
+            InstallSource::PyLauncher => Some("py launcher".to_string()),
+        }
+    }
+}
+
+/// Discovery results saved between runs.
+#[derive(Serialize, Deserialize)]
+struct DiscoveryCache {
+    created_at: u64,
+    /// What was scanned, so a changed `PATH` or config forces a rescan.
+    scanned: Vec<PathBuf>,
+    installs: Vec<PythonInstall>,
+}
+
+/// File name of the discovery cache, which shares the data directory with
+/// the logs.
+const DISCOVERY_CACHE_FILE: &str = "versions-cache.json";
+
+fn discovery_cache_path(ctx: &Context) -> PathBuf {
+    ctx.data_dir.join(DISCOVERY_CACHE_FILE)
+}
+
+/// Everything discovery looks at: the search path, the pyenv, asdf, uv,
+/// snap and flatpak install directories and the conda environment list.
+fn discovery_inputs(ctx: &Context) -> Vec<PathBuf> {
+    let mut inputs = python_search_dirs(ctx);
+    if ctx.env_python_dirs.is_some() {
+        return inputs;
+    }
+    inputs.extend(discovery::pyenv_root().map(|root| root.join("versions")));
+    inputs.extend(discovery::asdf_root().map(|root| root.join("installs").join("python")));
+    inputs.extend(discovery::uv_python_dir());
+    inputs.push(PathBuf::from(discovery::SNAP_BIN_DIR));
+    inputs.extend(
+        discovery::flatpak_dirs()
+            .into_iter()
+            .map(|dir| dir.join("runtime")),
+    );
+    inputs.extend(conda_environments_file());
+    inputs
+}
+
+fn modified_at(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|age| age.as_secs())
+}
+
+/// The cached installs, if the cache is younger than the TTL and none of
+/// the scanned locations changed since it was written.
+fn load_discovery_cache(ctx: &Context, scanned: &[PathBuf]) -> Option<Vec<PythonInstall>> {
+    let data = fs::read_to_string(discovery_cache_path(ctx)).ok()?;
+    let cache: DiscoveryCache = serde_json::from_str(&data).ok()?;
+    let age = current_timestamp().saturating_sub(cache.created_at);
+    if age >= ctx.discovery_ttl || cache.scanned != scanned {
+        return None;
+    }
+    if let Some(changed) = scanned
+        .iter()
+        .find(|path| modified_at(path).is_some_and(|mtime| mtime >= cache.created_at))
+    {
+        ctx.debug(&format!(
+            "discovery cache is stale: {} changed",
+            changed.display()
+        ));
+        return None;
+    }
+    Some(cache.installs)
+}
+
+/// Every interpreter on the search path and those installed by pyenv, asdf,
+/// uv and conda, sorted by version and without the excluded ones.
+fn get_python_versions(ctx: &Context) -> Vec<PythonInstall> {
+    discovery::filter_installs(ctx, discovered_installs(ctx))
+}
+
+/// The results of `discovery::discover`, cached for `discovery_ttl` seconds
+/// unless `--refresh` was given.
+fn discovered_installs(ctx: &Context) -> Vec<PythonInstall> {
+    let scanned = discovery_inputs(ctx);
+    if !ctx.refresh {
+        if let Some(installs) = load_discovery_cache(ctx, &scanned) {
+            return installs;
+        }
+    }
+
+    let installs = discovery::discover(ctx, &python_search_dirs(ctx));
+    if !ctx.dry_run && !ctx.read_only {
+        let cache = DiscoveryCache {
+            created_at: current_timestamp(),
+            scanned,
+            installs,
+        };
+        // The cache is only an optimisation, so failing to write it is fine.
+        let written = serde_json::to_string(&cache)
+            .map_err(io::Error::from)
+            .and_then(|data| {
+                fs::create_dir_all(&ctx.data_dir)?;
+                write_atomic(&discovery_cache_path(ctx), data.as_bytes())
+            });
+        if let Err(err) = written {
+            ctx.debug(&format!("could not write discovery cache: {}", err));
+        }
+        return cache.installs;
+    }
+    installs
+}
+
+/// Runs `command` to completion, killing it once `timeout` has passed.
+/// Returns `None` when it had to be killed.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Option<Output>> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read the pipes on their own threads so a chatty child cannot fill them
+    // up and stall while we wait for it.
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stdout = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let stderr = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        stderr.read_to_end(&mut output).map(|_| output)
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().expect("stdout reader panicked")?,
+        stderr: stderr.join().expect("stderr reader panicked")?,
+    }))
+}
+
+/// How long an interpreter may take to report its version.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs `python --version` and returns the `X.Y.Z` it reports, along with
+/// the implementation. Python 2 prints the version to stderr, so both
+/// streams are checked.
+fn probe_version(path: &Path) -> Result<(PythonVersion, Implementation), String> {
+    let output = output_with_timeout(Command::new(path).arg("--version"), PROBE_TIMEOUT)
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("no answer within {} seconds", PROBE_TIMEOUT.as_secs()))?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let implementation = if text.contains("PyPy") {
+        Implementation::PyPy
+    } else {
+        Implementation::CPython
+    };
+    Regex::new(r"Python (\d+\.\d+\.\d+)")
+        .unwrap()
+        .captures(&text)
+        .and_then(|caps| PythonVersion::parse(&caps[1]))
+        .map(|version| (version, implementation))
+        .ok_or_else(|| "unrecognised --version output".to_string())
+}
+
+/// Asks the interpreter for the machine it was built for and its compiler.
+fn probe_build_info(path: &Path) -> Result<BuildInfo, String> {
+    let output = output_with_timeout(
+        Command::new(path).args([
+            "-c",
+            "import platform,json;print(json.dumps([platform.machine(), platform.python_compiler()]))",
+        ]),
+        PROBE_TIMEOUT,
+    )
+    .map_err(|err| err.to_string())?
+    .ok_or_else(|| format!("no answer within {} seconds", PROBE_TIMEOUT.as_secs()))?;
+    let (architecture, compiler): (String, String) =
+        serde_json::from_slice(&output.stdout).map_err(|_| "unrecognised platform output")?;
+    Ok(BuildInfo {
+        architecture,
+        compiler,
+    })
+}
+
+/// Everything probing learns about one interpreter.
+fn probe_install(path: &Path) -> (Result<(PythonVersion, Implementation), String>, BuildInfo) {
+    let version = probe_version(path);
+    // An interpreter too old or broken for the platform query is still
+    // listed, just without build details.
+    let build = probe_build_info(path).unwrap_or_else(|_| BuildInfo::unknown());
+    (version, build)
+}
+
+/// Replaces the filename-derived versions of `installs` with what the
+/// interpreters report themselves, keeping the old version with a warning
+/// for those that cannot be probed, and records their build details. Up to
+/// `jobs` interpreters are run at once; results and warnings keep the order
+/// of `installs`.
+fn probe_installs(installs: &mut [PythonInstall], jobs: usize) {
+    let results: Vec<_> = if jobs <= 1 {
+        installs
+            .iter()
+            .map(|install| probe_install(&install.path))
+            .collect()
+    } else {
+        let next = AtomicUsize::new(0);
+        let slots: Vec<Mutex<Option<_>>> = installs.iter().map(|_| Mutex::new(None)).collect();
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.min(installs.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(install) = installs.get(index) else {
+                        break;
+                    };
+                    *slots[index].lock().unwrap() = Some(probe_install(&install.path));
+                });
+            }
+        });
+        slots
+            .into_iter()
+            .map(|slot| slot.into_inner().unwrap().expect("every install is probed"))
+            .collect()
+    };
+
+    for (install, (result, build)) in installs.iter_mut().zip(results) {
+        install.build = Some(build);
+        match result {
+            Ok((full_version, implementation)) => {
+                install.version = full_version.minor_only();
+                install.full_version = Some(full_version);
+                install.implementation = implementation;
+            }
+            Err(err) => eprintln!(
+                "warning: could not probe {}: {}",
+                install.path.display(),
+                err
+            ),
+        }
+    }
+}
+
+/// The distinct log keys of `installs`, in discovery order.
+fn version_names(installs: &[PythonInstall]) -> Vec<String> {
+    let mut versions: Vec<String> = Vec::new();
+    for install in installs {
+        let key = install.log_key();
+        if !versions.contains(&key) {
+            versions.push(key);
+        }
+    }
+    versions
+}
+
+/// A conda environment that contains a Python interpreter.
+struct CondaEnv {
+    name: String,
+    /// The `X.Y` form used to name logs.
+    version: PythonVersion,
+    full_version: PythonVersion,
+    prefix: PathBuf,
+    path: PathBuf,
+}
+
+/// The `conda` executable, if conda is installed.
+fn conda_binary() -> Option<PathBuf> {
+    std::env::var_os("CONDA_EXE")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("PATH").and_then(|path| {
+                std::env::split_paths(&path)
+                    .map(|dir| dir.join("conda"))
+                    .find(|path| path.is_file())
+            })
+        })
+        .filter(|path| path.is_file())
+}
+
+/// Reads the Python version of a conda environment from its package
+/// records, only running the interpreter when there are none.
+fn conda_python_version(prefix: &Path, python: &Path) -> Option<String> {
+    let re = Regex::new(r"^python-(\d+\.\d+\.\d+)-.*\.json$").unwrap();
+    let recorded = fs::read_dir(prefix.join("conda-meta"))
+        .ok()
+        .and_then(|entries| {
+            entries.flatten().find_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                re.captures(&name).map(|caps| caps[1].to_string())
+            })
+        });
+    recorded.or_else(|| {
+        // Python 2 prints its version to stderr.
+        let output = output_with_timeout(Command::new(python).arg("--version"), PROBE_TIMEOUT)
+            .ok()
+            .flatten()?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Regex::new(r"(\d+\.\d+\.\d+)")
+            .unwrap()
+            .captures(&text)
+            .map(|caps| caps[1].to_string())
+    })
+}
+
+fn conda_environments_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| Path::new(&home).join(".conda").join("environments.txt"))
+}
+
+/// Environments listed in `~/.conda/environments.txt`. With `query_conda`,
+/// also asks `conda env list --json`, which is too slow to do on every
+/// command.
+fn conda_envs(query_conda: bool) -> Vec<CondaEnv> {
+    let mut prefixes: Vec<PathBuf> = conda_environments_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|data| {
+            data.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if let Some(conda) = conda_binary().filter(|_| query_conda) {
+        let listed = output_with_timeout(
+            Command::new(conda).args(["env", "list", "--json"]),
+            PROBE_TIMEOUT,
+        )
+        .ok()
+        .flatten()
+        .filter(|output| output.status.success())
+        .and_then(|output| serde_json::from_slice::<serde_json::Value>(&output.stdout).ok());
+        if let Some(envs) = listed.as_ref().and_then(|value| value["envs"].as_array()) {
+            prefixes.extend(
+                envs.iter()
+                    .filter_map(|env| env.as_str())
+                    .map(PathBuf::from),
+            );
+        }
+    }
+
+    let mut seen = BTreeSet::new();
+    prefixes.retain(|prefix| seen.insert(prefix.clone()));
+    prefixes
+        .into_iter()
+        .filter_map(|prefix| {
+            let path = if cfg!(windows) {
+                prefix.join("python.exe")
+            } else {
+                prefix.join("bin").join("python")
+            };
+            if !path.is_file() {
+                return None;
+            }
+            let full_version = PythonVersion::parse(&conda_python_version(&prefix, &path)?)?;
+            let version = full_version.minor_only();
+            // Named environments live in `<root>/envs/<name>`; the root
+            // prefix itself is the base environment.
+            let name = match prefix.parent().and_then(Path::file_name) {
+                Some(parent) if parent == "envs" => prefix
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                _ => "base".to_string(),
+            };
+            Some(CondaEnv {
+                name,
+                version,
+                full_version,
+                prefix,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Reads the Python version a virtualenv was created with from its
+/// `pyvenv.cfg`, which has `version` (venv) or `version_info` (virtualenv).
+fn venv_version(venv: &Path) -> Option<PythonVersion> {
+    let config = fs::read_to_string(venv.join("pyvenv.cfg")).ok()?;
+    config.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        match key.trim() {
+            "version" | "version_info" => PythonVersion::parse(value),
+            _ => None,
+        }
+    })
+}
+
+/// Virtualenvs directly inside the configured venv roots, and `.venv`
+/// directories of the projects there, labelled with the directory name.
+/// These are not part of the default discovery since they mostly repeat the
+/// interpreter they were created from.
+fn venv_installs(ctx: &Context) -> Vec<PythonInstall> {
+    let mut installs = Vec::new();
+    for root in &ctx.venv_roots {
+        let Ok(entries) = fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let venv = if dir.join("pyvenv.cfg").is_file() {
+                dir.clone()
+            } else {
+                dir.join(".venv")
+            };
+            let path = venv_python(&venv);
+            let Some(full_version) = venv_version(&venv).filter(|_| path.exists()) else {
+                continue;
+            };
+            installs.push(PythonInstall {
+                version: full_version.minor_only(),
+                implementation: Implementation::CPython,
+                full_version: Some(full_version),
+                path,
+                aliases: Vec::new(),
+                source: InstallSource::Venv,
+                env_name: Some(entry.file_name().to_string_lossy().into_owned()),
+                build: None,
+            });
+        }
+    }
+    installs.sort_by(|a, b| a.env_name.cmp(&b.env_name));
+    installs
+}
+
+/// Conda environments and virtualenvs as (name, version, location, kind).
+fn environment_rows(
+    ctx: &Context,
+    query_conda: bool,
+) -> Vec<(String, String, PathBuf, &'static str)> {
+    let mut rows: Vec<_> = conda_envs(query_conda)
+        .into_iter()
+        .map(|env| (env.name, env.full_version.to_string(), env.prefix, "conda"))
+        .collect();
+    rows.extend(venv_installs(ctx).into_iter().map(|install| {
+        let location = install
+            .path
+            .parent()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| install.path.clone());
+        (
+            install.env_name.unwrap_or_default(),
+            install.full_version.unwrap_or(install.version).to_string(),
+            location,
+            "venv",
+        )
+    }));
+    rows
+}
+
+fn format_environment_rows(rows: &[(String, String, PathBuf, &str)]) -> Vec<String> {
+    let width = rows.iter().map(|(name, ..)| name.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(name, version, location, kind)| {
+            format!(
+                "{:width$}  {:8}  {:5}  {}",
+                name,
+                version,
+                kind,
+                location.display(),
+                width = width
+            )
+        })
+        .collect()
+}
+
+fn list_envs(ctx: &Context) {
+    let rows = environment_rows(ctx, true);
+    if rows.is_empty() {
+        println!("No environments found.");
+        return;
+    }
+    for line in format_environment_rows(&rows) {
+        println!("{}", line);
+    }
+}
+
+/// Interpreter discovery for Windows, where interpreters are not named
+/// `pythonX.Y` and nothing lives in `/usr/bin`. The `py -0p` parser is pure,
+/// so it is built for tests on every platform.
+#[cfg(any(windows, test))]
+mod windows {
+    use super::PythonVersion;
+    #[cfg(windows)]
+    use super::{Implementation, InstallSource, PythonInstall};
+    use regex::Regex;
+    #[cfg(windows)]
+    use std::fs;
+    use std::path::PathBuf;
+    #[cfg(windows)]
+    use std::process::Command;
+
+    /// Interpreters known to the `py` launcher, followed by per-user installs
+    /// under `%LOCALAPPDATA%\Programs\Python` that it does not list.
+    #[cfg(windows)]
+    pub(crate) fn python_installs() -> Vec<PythonInstall> {
+        let installs: Vec<PythonInstall> = Command::new("py")
+            .arg("-0p")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| parse_py_list(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(version, path)| PythonInstall {
+                version,
+                full_version: None,
+                path,
+                aliases: Vec::new(),
+                implementation: Implementation::CPython,
+                source: InstallSource::PyLauncher,
+                env_name: None,
+                build: None,
+            })
+            .collect();
+        // Duplicates with the launcher's list are folded by `dedupe_installs`.
+        installs
+            .into_iter()
+            .chain(local_app_data_installs())
+            .collect()
+    }
+
+    /// Parses the output of `py -0p`, which lists one interpreter per line:
+    ///
+    /// ```text
+    ///  -V:3.12 *        C:\Python312\python.exe
+    ///  -3.9-64          C:\Python39\python.exe
+    /// ```
+    ///
+    /// Newer launchers use the `-V:` form, older ones the bare one; the `*`
+    /// marks the default interpreter.
+    pub(crate) fn parse_py_list(output: &str) -> Vec<(PythonVersion, PathBuf)> {
+        let re = Regex::new(r"^\s*-(?:V:)?(\d+)\.(\d+)\S*\s+(?:\*\s+)?(\S.*?)\s*$").unwrap();
+        output
+            .lines()
+            .filter_map(|line| {
+                let caps = re.captures(line)?;
+                let version = PythonVersion::parse(&format!("{}.{}", &caps[1], &caps[2]))?;
+                Some((version, PathBuf::from(&caps[3])))
+            })
+            .collect()
+    }
+
+    /// Installs made by the python.org installer for the current user, in
+    /// directories named like `Python312`.
+    #[cfg(windows)]
+    fn local_app_data_installs() -> Vec<PythonInstall> {
+        let Some(dir) = std::env::var_os("LOCALAPPDATA")
+            .map(|dir| PathBuf::from(dir).join("Programs").join("Python"))
+        else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let re = Regex::new(r"^Python(\d)(\d+)").unwrap();
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let caps = re.captures(&name)?;
+                let path = entry.path().join("python.exe");
+                let version = PythonVersion::parse(&format!("{}.{}", &caps[1], &caps[2]))?;
+                path.is_file().then(|| PythonInstall {
+                    version,
+                    full_version: None,
+                    path,
+                    aliases: Vec::new(),
+                    implementation: Implementation::CPython,
+                    source: InstallSource::Path,
+                    env_name: None,
+                    build: None,
+                })
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::parse_py_list;
+        use std::path::PathBuf;
+
+        fn parsed(output: &str) -> Vec<(String, PathBuf)> {
+            parse_py_list(output)
+                .into_iter()
+                .map(|(version, path)| (version.to_string(), path))
+                .collect()
+        }
+
+        #[test]
+        fn parses_current_launcher_output() {
+            let output = " -V:3.12 *        C:\\Python312\\python.exe\r\n \
+                          -V:3.11          C:\\Program Files\\Python311\\python.exe\r\n";
+            assert_eq!(
+                parsed(output),
+                vec![
+                    (
+                        "3.12".to_string(),
+                        PathBuf::from("C:\\Python312\\python.exe")
+                    ),
+                    (
+                        "3.11".to_string(),
+                        PathBuf::from("C:\\Program Files\\Python311\\python.exe")
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn parses_legacy_launcher_output() {
+            let output = "Installed Pythons found by py Launcher for Windows\n \
+                          -3.12-64 *       C:\\Python312\\python.exe\n \
+                          -3.9-32          C:\\Python39-32\\python.exe\n";
+            assert_eq!(
+                parsed(output),
+                vec![
+                    (
+                        "3.12".to_string(),
+                        PathBuf::from("C:\\Python312\\python.exe")
+                    ),
+                    (
+                        "3.9".to_string(),
+                        PathBuf::from("C:\\Python39-32\\python.exe")
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn skips_blank_and_unrecognised_lines() {
+            let output = "\n   \nNo installed Pythons found!\n \
+                          -V:Astral/CPython3.12  C:\\uv\\python.exe\n \
+                          -V:3.13t         C:\\Python313\\python3.13t.exe\ngarbage\n";
+            assert_eq!(
+                parsed(output),
+                vec![(
+                    "3.13".to_string(),
+                    PathBuf::from("C:\\Python313\\python3.13t.exe")
+                )]
+            );
+            assert!(parsed("").is_empty());
+        }
+    }
+}
+
+/// Finding interpreters: on the search path, in the pyenv, asdf and uv
+/// install directories and among the conda environments. Use
+/// `get_python_versions`, which caches the results, rather than calling
+/// into this directly.
+mod discovery {
+    use super::{
+        conda_envs, probe_version, Context, Implementation, InstallSource, PythonInstall,
+        PythonVersion,
+    };
+    use regex::Regex;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// Every interpreter in `dirs` and those installed by snap, flatpak,
+    /// pyenv, asdf, uv and conda, with duplicates folded and sorted by
+    /// version.
+    pub(crate) fn discover(ctx: &Context, dirs: &[PathBuf]) -> Vec<PythonInstall> {
+        let mut skipped = 0;
+        // Snap wrappers are covered by `snap_installs`.
+        let mut installs: Vec<PythonInstall> = dirs
+            .iter()
+            .filter(|dir| dir.as_path() != Path::new(SNAP_BIN_DIR))
+            .flat_map(|dir| installs_from_listing(&list_dir(ctx, dir, &mut skipped)))
+            .collect();
+        // `PYMANAGER_PYTHON_DIRS` names every place to look.
+        if ctx.env_python_dirs.is_none() {
+            installs.extend(managed_installs(ctx, &mut skipped));
+        }
+        if skipped > 0 {
+            ctx.debug(&format!("skipped {} unusable interpreter entries", skipped));
+        }
+        let mut installs = dedupe_installs(installs);
+        for install in &mut installs {
+            tag_nix_store(install);
+        }
+        // Stable, so the search order still decides between installs of the
+        // same version.
+        installs.sort_by_key(|install| (install.version, install.implementation));
+        installs
+    }
+
+    /// Interpreters put in place by snap, flatpak, the Windows launcher,
+    /// pyenv, asdf, uv and conda.
+    fn managed_installs(ctx: &Context, skipped: &mut usize) -> Vec<PythonInstall> {
+        let mut installs = snap_installs();
+        installs.extend(flatpak_installs(ctx, skipped));
+        #[cfg(windows)]
+        installs.extend(super::windows::python_installs());
+        if let Some(root) = pyenv_root() {
+            installs.extend(versioned_installs(
+                &root.join("versions"),
+                InstallSource::Pyenv,
+            ));
+        }
+        if let Some(root) = asdf_root() {
+            installs.extend(versioned_installs(
+                &root.join("installs").join("python"),
+                InstallSource::Asdf,
+            ));
+        }
+        installs.extend(uv_installs());
+        installs.extend(conda_envs(false).into_iter().map(|env| PythonInstall {
+            version: env.version,
+            full_version: Some(env.full_version),
+            path: env.path,
+            aliases: Vec::new(),
+            implementation: Implementation::CPython,
+            source: InstallSource::Conda,
+            env_name: Some(env.name),
+            build: None,
+        }));
+        installs
+    }
+
+    const NIX_STORE: &str = "/nix/store";
+
+    /// Marks interpreters found on the search path that resolve into the
+    /// Nix store, taking the full version from the store path name, such as
+    /// `/nix/store/<hash>-python3-3.11.6/bin/python3.11`.
+    fn tag_nix_store(install: &mut PythonInstall) {
+        if install.source != InstallSource::Path {
+            return;
+        }
+        let Some(package) = install
+            .path
+            .strip_prefix(NIX_STORE)
+            .ok()
+            .and_then(|relative| relative.components().next())
+            .map(|package| package.as_os_str().to_string_lossy().into_owned())
+        else {
+            return;
+        };
+        install.source = InstallSource::Nix;
+        let re = Regex::new(r"^[0-9a-z]+-(?:python|pypy)\d*-(\d+\.\d+\.\d+)(?:-[a-z]+)?$").unwrap();
+        if install.full_version.is_none() {
+            install.full_version = re
+                .captures(&package)
+                .and_then(|caps| PythonVersion::parse(&caps[1]))
+                .filter(|full_version| full_version.minor_only() == install.version);
+        }
+    }
+
+    /// Where snapd puts the commands of installed snaps.
+    pub(crate) const SNAP_BIN_DIR: &str = "/snap/bin";
+
+    /// Interpreters provided by snaps. Their commands link to the `snap`
+    /// launcher rather than to the interpreter, so the version comes from
+    /// the command name.
+    fn snap_installs() -> Vec<PythonInstall> {
+        let Ok(entries) = fs::read_dir(SNAP_BIN_DIR) else {
+            return Vec::new();
+        };
+        let listing: Vec<(PathBuf, PathBuf)> = entries
+            .flatten()
+            .map(|entry| (entry.path(), entry.path()))
+            .filter(|(path, _)| path.exists())
+            .collect();
+        installs_from_listing(&listing)
+            .into_iter()
+            .map(|install| PythonInstall {
+                source: InstallSource::Snap,
+                ..install
+            })
+            .collect()
+    }
+
+    /// System-wide and per-user flatpak installations.
+    pub(crate) fn flatpak_dirs() -> Vec<PathBuf> {
+        let mut dirs = vec![PathBuf::from("/var/lib/flatpak")];
+        dirs.extend(std::env::var_os("HOME").map(|home| {
+            Path::new(&home)
+                .join(".local")
+                .join("share")
+                .join("flatpak")
+        }));
+        dirs.retain(|dir| dir.is_dir());
+        dirs
+    }
+
+    /// Interpreters shipped in flatpak runtimes, which live in
+    /// `runtime/<id>/<arch>/<branch>/active/files/bin`. They are tagged with
+    /// the runtime they belong to.
+    fn flatpak_installs(ctx: &Context, skipped: &mut usize) -> Vec<PythonInstall> {
+        let subdirs = |dir: &Path| -> Vec<PathBuf> {
+            fs::read_dir(dir)
+                .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+                .unwrap_or_default()
+        };
+        let mut installs = Vec::new();
+        for root in flatpak_dirs() {
+            for runtime in subdirs(&root.join("runtime")) {
+                let id = runtime
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                for branch in subdirs(&runtime).iter().flat_map(|arch| subdirs(arch)) {
+                    let name = format!(
+                        "{}//{}",
+                        id,
+                        branch.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                    let bin = branch.join("active").join("files").join("bin");
+                    installs.extend(
+                        installs_from_listing(&list_dir(ctx, &bin, skipped))
+                            .into_iter()
+                            .map(|install| PythonInstall {
+                                source: InstallSource::Flatpak,
+                                env_name: Some(name.clone()),
+                                ..install
+                            }),
+                    );
+                }
+            }
+        }
+        installs
+    }
+
+    /// Drops the installs ruled out by `--min-version` and the exclude
+    /// patterns, which are matched against binary names. Aliases matching a
+    /// pattern are dropped on their own.
+    pub(crate) fn filter_installs(
+        ctx: &Context,
+        mut installs: Vec<PythonInstall>,
+    ) -> Vec<PythonInstall> {
+        let matching_pattern = |path: &Path| {
+            let name = path.file_name()?.to_string_lossy();
+            ctx.exclude_patterns
+                .iter()
+                .find(|pattern| pattern.is_match(&name))
+                .map(|pattern| pattern.as_str().to_string())
+        };
+        installs.retain_mut(|install| {
+            if let Some(min_version) = ctx.min_version {
+                if install.version < min_version {
+                    ctx.debug(&format!(
+                        "excluding {}: older than {}",
+                        install.path.display(),
+                        min_version
+                    ));
+                    return false;
+                }
+            }
+            if let Some(pattern) = matching_pattern(&install.path) {
+                ctx.debug(&format!(
+                    "excluding {}: matches '{}'",
+                    install.path.display(),
+                    pattern
+                ));
+                return false;
+            }
+            install
+                .aliases
+                .retain(|alias| match matching_pattern(alias) {
+                    Some(pattern) => {
+                        ctx.debug(&format!(
+                            "excluding {}: matches '{}'",
+                            alias.display(),
+                            pattern
+                        ));
+                        false
+                    }
+                    None => true,
+                });
+            true
+        });
+        installs
+    }
+
+    /// The entries of `dir` that look like interpreters, each paired with
+    /// the file it resolves to. Broken symlinks and files that cannot be
+    /// executed are skipped and counted in `skipped`.
+    fn list_dir(ctx: &Context, dir: &Path, skipped: &mut usize) -> Vec<(PathBuf, PathBuf)> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut listing = Vec::new();
+        for entry in entries.flatten() {
+            if !is_interpreter_name(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let path = entry.path();
+            let problem = match fs::canonicalize(&path) {
+                Ok(resolved) if is_executable(&resolved) => {
+                    listing.push((path, resolved));
+                    continue;
+                }
+                Ok(_) => "not an executable file",
+                Err(_) => "broken symlink",
+            };
+            ctx.debug(&format!("skipping {}: {}", path.display(), problem));
+            *skipped += 1;
+        }
+        listing
+    }
+
+    /// Whether `path` is a regular file that may be run. Windows has no
+    /// executable bit, so any file counts there.
+    fn is_executable(path: &Path) -> bool {
+        let Ok(meta) = fs::metadata(path) else {
+            return false;
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            meta.is_file() && meta.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        {
+            meta.is_file()
+        }
+    }
+
+    /// Whether `name` may be an interpreter. Helpers installed next to it,
+    /// such as `python3.11-config` and `python3.11-gdb.py`, are not.
+    fn is_interpreter_name(name: &str) -> bool {
+        (name.starts_with("python") || name.starts_with("pypy"))
+            && !name.ends_with("-config")
+            && !name.ends_with(".py")
+    }
+
+    /// Turns a directory listing of `(entry, resolved)` paths into
+    /// interpreters. The version is taken from the resolved name so
+    /// `python3` counts as whatever it points to; suffixes such as
+    /// Homebrew's `python3.12-intel64` are ignored.
+    fn installs_from_listing(listing: &[(PathBuf, PathBuf)]) -> Vec<PythonInstall> {
+        let re = Regex::new(r"(python|pypy)(\d+)\.(\d+)").unwrap();
+        let file_name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        listing
+            .iter()
+            .filter_map(|(entry, resolved)| {
+                let entry_name = file_name(entry);
+                if !is_interpreter_name(&entry_name) {
+                    return None;
+                }
+                let resolved_name = file_name(resolved);
+                let caps = re
+                    .captures(&resolved_name)
+                    .or_else(|| re.captures(&entry_name))?;
+                Some(PythonInstall {
+                    version: PythonVersion::parse(&caps[0])?,
+                    implementation: if &caps[1] == "pypy" {
+                        Implementation::PyPy
+                    } else {
+                        Implementation::CPython
+                    },
+                    full_version: None,
+                    path: entry.clone(),
+                    aliases: Vec::new(),
+                    source: InstallSource::Path,
+                    env_name: None,
+                    build: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Resolves every install to its real binary and folds installs that turn
+    /// out to be the same file into the first one, keeping the other names as
+    /// aliases.
+    fn dedupe_installs(candidates: Vec<PythonInstall>) -> Vec<PythonInstall> {
+        let mut installs: Vec<PythonInstall> = Vec::new();
+        for mut candidate in candidates {
+            // Snap commands are all links to the `snap` launcher, which picks
+            // the snap by the name it was run as, so they are kept as found.
+            let resolved = if candidate.source == InstallSource::Snap {
+                candidate.path.clone()
+            } else {
+                match fs::canonicalize(&candidate.path) {
+                    Ok(resolved) => resolved,
+                    Err(_) => continue,
+                }
+            };
+            let name = std::mem::replace(&mut candidate.path, resolved);
+            match installs
+                .iter_mut()
+                .find(|install| install.path == candidate.path)
+            {
+                Some(known) => {
+                    if name != known.path && !known.aliases.contains(&name) {
+                        known.aliases.push(name);
+                    }
+                }
+                None => {
+                    if name != candidate.path {
+                        candidate.aliases.push(name);
+                    }
+                    installs.push(candidate);
+                }
+            }
+        }
+        installs
+    }
+
+    pub(crate) fn pyenv_root() -> Option<PathBuf> {
+        std::env::var_os("PYENV_ROOT")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".pyenv")))
+    }
+
+    pub(crate) fn asdf_root() -> Option<PathBuf> {
+        std::env::var_os("ASDF_DATA_DIR")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".asdf")))
+    }
+
+    /// Where uv keeps the interpreters it manages.
+    pub(crate) fn uv_python_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("UV_PYTHON_INSTALL_DIR") {
+            return Some(PathBuf::from(dir));
+        }
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+            })
+            .map(|dir| dir.join("uv").join("python"))
+    }
+
+    /// Parses a uv install directory name such as
+    /// `cpython-3.12.4-linux-x86_64-gnu` or `pypy-3.10.14-macos-aarch64-none`.
+    fn parse_uv_dir_name(name: &str) -> Option<(PythonVersion, Implementation)> {
+        let re = Regex::new(r"^(cpython|pypy)-(\d+\.\d+\.\d+)[^-]*-").unwrap();
+        let caps = re.captures(name)?;
+        let implementation = match &caps[1] {
+            "pypy" => Implementation::PyPy,
+            _ => Implementation::CPython,
+        };
+        Some((PythonVersion::parse(&caps[2])?, implementation))
+    }
+
+    /// Interpreters installed by uv. Directories whose name does not parse are
+    /// probed instead.
+    fn uv_installs() -> Vec<PythonInstall> {
+        let Some(entries) = uv_python_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = if cfg!(windows) {
+                    entry.path().join("python.exe")
+                } else {
+                    entry.path().join("bin").join("python3")
+                };
+                if !is_executable(&path) {
+                    return None;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let (full_version, implementation) =
+                    parse_uv_dir_name(&name).or_else(|| probe_version(&path).ok())?;
+                Some(PythonInstall {
+                    version: full_version.minor_only(),
+                    implementation,
+                    full_version: Some(full_version),
+                    path,
+                    aliases: Vec::new(),
+                    source: InstallSource::Uv,
+                    env_name: None,
+                    build: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Interpreters in a directory of `<X.Y.Z>/bin/python` installs, as kept by
+    /// pyenv and asdf, newest first. Entries that are not plain CPython versions
+    /// (virtualenvs, conda builds) are skipped.
+    fn versioned_installs(dir: &Path, source: InstallSource) -> Vec<PythonInstall> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let re = Regex::new(r"^\d+\.\d+\.\d+$").unwrap();
+        let mut installs: Vec<PythonInstall> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let full_version = PythonVersion::parse(re.find(&name)?.as_str())?;
+                let path = entry.path().join("bin").join("python");
+                is_executable(&path).then(|| PythonInstall {
+                    version: full_version.minor_only(),
+                    full_version: Some(full_version),
+                    path,
+                    aliases: Vec::new(),
+                    implementation: Implementation::CPython,
+                    source,
+                    env_name: None,
+                    build: None,
+                })
+            })
+            .collect();
+        installs.sort_by_key(|install| std::cmp::Reverse(install.full_version));
+        installs
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn version(text: &str) -> PythonVersion {
+            PythonVersion::parse(text).unwrap()
+        }
+
+        fn listing(pairs: &[(&str, &str)]) -> Vec<(PathBuf, PathBuf)> {
+            pairs
+                .iter()
+                .map(|(entry, resolved)| (PathBuf::from(entry), PathBuf::from(resolved)))
+                .collect()
+        }
+
+        #[test]
+        fn reads_versions_from_a_listing() {
+            let installs = installs_from_listing(&listing(&[
+                ("/bin/python3", "/usr/bin/python3.11"),
+                ("/bin/python3.11", "/usr/bin/python3.11"),
+                ("/bin/python3.11-config", "/usr/bin/python3.11-config"),
+                ("/bin/python3.11-gdb.py", "/usr/share/gdb/python3.11-gdb.py"),
+                ("/bin/pypy3.10", "/opt/pypy/bin/pypy3.10"),
+                ("/bin/python", "/usr/bin/python"),
+                ("/bin/pip3.11", "/usr/bin/pip3.11"),
+            ]));
+            let found: Vec<(String, &Path)> = installs
+                .iter()
+                .map(|install| (install.log_key(), install.path.as_path()))
+                .collect();
+            assert_eq!(
+                found,
+                vec![
+                    ("3.11".to_string(), Path::new("/bin/python3")),
+                    ("3.11".to_string(), Path::new("/bin/python3.11")),
+                    ("pypy3.10".to_string(), Path::new("/bin/pypy3.10")),
+                ]
+            );
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn discovers_interpreters_in_a_directory() {
+            use crate::tests::{fake_interpreter, test_context};
+            let dir = tempfile::tempdir().unwrap();
+            let bin = &fs::canonicalize(dir.path()).unwrap();
+            fake_interpreter(&bin.join("python3.11"));
+            fake_interpreter(&bin.join("python3.9"));
+            fake_interpreter(&bin.join("python3.11-config"));
+            std::os::unix::fs::symlink("python3.11", bin.join("python3")).unwrap();
+
+            let data = tempfile::tempdir().unwrap();
+            let installs = discover(&test_context(data.path()), &[bin.to_path_buf()]);
+            let found: Vec<(String, PathBuf, Vec<PathBuf>)> = installs
+                .into_iter()
+                .map(|install| (install.log_key(), install.path, install.aliases))
+                .collect();
+            assert_eq!(
+                found,
+                vec![
+                    ("3.9".to_string(), bin.join("python3.9"), Vec::new()),
+                    (
+                        "3.11".to_string(),
+                        bin.join("python3.11"),
+                        vec![bin.join("python3")]
+                    ),
+                ]
+            );
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn skips_broken_and_non_executable_entries() {
+            use crate::tests::{fake_interpreter, test_context};
+            use std::os::unix::fs::symlink;
+            let dir = tempfile::tempdir().unwrap();
+            let bin = &fs::canonicalize(dir.path()).unwrap();
+            fake_interpreter(&bin.join("python3.12"));
+            // Left behind by an uninstall.
+            symlink(bin.join("gone/python3.8"), bin.join("python3.8")).unwrap();
+            symlink("python3.8", bin.join("python3")).unwrap();
+            fs::write(bin.join("python3.7"), "").unwrap();
+            fs::create_dir(bin.join("python3.6")).unwrap();
+
+            let data = tempfile::tempdir().unwrap();
+            let ctx = test_context(data.path());
+            let mut skipped = 0;
+            let listing = list_dir(&ctx, bin, &mut skipped);
+            assert_eq!(
+                listing,
+                vec![(bin.join("python3.12"), bin.join("python3.12"))]
+            );
+            assert_eq!(skipped, 4);
+
+            let versions: Vec<String> = discover(&ctx, &[bin.to_path_buf()])
+                .iter()
+                .map(PythonInstall::log_key)
+                .collect();
+            assert_eq!(versions, vec!["3.12"]);
+        }
+
+        #[test]
+        fn parses_uv_install_names() {
+            assert_eq!(
+                parse_uv_dir_name("cpython-3.12.4-linux-x86_64-gnu"),
+                Some((version("3.12.4"), Implementation::CPython))
+            );
+            assert_eq!(
+                parse_uv_dir_name("cpython-3.11.9-macos-aarch64-none"),
+                Some((version("3.11.9"), Implementation::CPython))
+            );
+            assert_eq!(
+                parse_uv_dir_name("cpython-3.10.14-windows-x86_64-none"),
+                Some((version("3.10.14"), Implementation::CPython))
+            );
+            assert_eq!(
+                parse_uv_dir_name("pypy-3.10.14-linux-x86_64-gnu"),
+                Some((version("3.10.14"), Implementation::PyPy))
+            );
+        }
+
+        #[test]
+        fn parses_uv_names_with_build_suffixes() {
+            assert_eq!(
+                parse_uv_dir_name("cpython-3.13.0+freethreaded-linux-x86_64-gnu"),
+                Some((version("3.13.0"), Implementation::CPython))
+            );
+            assert_eq!(
+                parse_uv_dir_name("cpython-3.14.0a1-linux-aarch64-musl"),
+                Some((version("3.14.0"), Implementation::CPython))
+            );
+        }
+
+        #[test]
+        fn rejects_other_uv_entries() {
+            for name in [
+                ".lock",
+                ".cache",
+                ".temp",
+                "cpython-3.12-linux-x86_64-gnu",
+                "cpython-3.12.4",
+                "graalpy-24.0.0-linux-x86_64-gnu",
+                "python-3.12.4-linux-x86_64-gnu",
+            ] {
+                assert_eq!(parse_uv_dir_name(name), None, "{}", name);
+            }
+        }
+    }
+}
+
+/// Path of the interpreter for `version`, preferring the search path over
+/// pyenv, asdf and conda installs.
+fn python_interpreter(ctx: &Context, version: &str) -> Option<PathBuf> {
+    get_python_versions(ctx)
+        .into_iter()
+        .find(|install| install.log_key() == version)
+        .map(|install| install.path)
+}
+
+/// Where logs were kept before they moved to the per-user data directory.
+const LEGACY_LOG_DIR: &str = "/var/log/pymanager";
+
+/// The per-user data directory: `%LOCALAPPDATA%\pymanager` on Windows,
+/// `~/Library/Application Support/pymanager` on macOS and
+/// `$XDG_DATA_HOME/pymanager` (`~/.local/share/pymanager`) elsewhere.
+fn user_data_dir() -> PathBuf {
+    let home = || {
+        std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+    };
+    if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join("pymanager")
+    } else if cfg!(target_os = "macos") {
+        home()
+            .join("Library")
+            .join("Application Support")
+            .join("pymanager")
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home().join(".local").join("share"))
+            .join("pymanager")
+    }
+}
+
+fn is_writable_dir(dir: &Path) -> bool {
+    let probe = dir.join(format!(".pymanager-write-test-{}", std::process::id()));
+    let writable = fs::write(&probe, b"").is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
+
+/// Copies the files of the legacy log directory into `data_dir`, returning
+/// how many were copied. The legacy directory is left alone.
+fn migrate_legacy_logs(legacy: &Path, data_dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(data_dir)?;
+    let mut copied = 0;
+    for entry in fs::read_dir(legacy)?.flatten() {
+        if entry.file_type()?.is_file() && entry.file_name() != DISCOVERY_CACHE_FILE {
+            fs::copy(entry.path(), data_dir.join(entry.file_name()))?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Picks the directory holding the logs. The first run after logs moved to
+/// the per-user data directory copies them over from `/var/log/pymanager`;
+/// if that fails, the old directory keeps being used as long as it is
+/// writable.
+fn resolve_data_dir(dry_run: bool) -> PathBuf {
+    let data_dir = user_data_dir();
+    let legacy = Path::new(LEGACY_LOG_DIR);
+    if cfg!(windows) || data_dir.exists() || !legacy.is_dir() {
+        return data_dir;
+    }
+    if dry_run {
+        return legacy.to_path_buf();
+    }
+    match migrate_legacy_logs(legacy, &data_dir) {
+        Ok(copied) => {
+            eprintln!(
+                "note: copied {} file(s) from {} to {}",
+                copied,
+                legacy.display(),
+                data_dir.display()
+            );
+            data_dir
+        }
+        Err(err) if is_writable_dir(legacy) => {
+            eprintln!(
+                "warning: could not move logs to {}: {}; still using {}",
+                data_dir.display(),
+                err,
+                legacy.display()
+            );
+            legacy.to_path_buf()
+        }
+        Err(_) => data_dir,
+    }
+}
+
+/// Loads the log of `version`. A log that cannot be read is fatal, as
+/// carrying on could overwrite it.
+fn load_project_log(ctx: &Context, version: &str) -> ProjectLog {
+    try_load_project_log(ctx, version).unwrap_or_else(|err| ctx.exit_with_error(&err.to_string()))
+}
+
+/// Why a log could not be loaded.
+#[derive(Debug)]
+enum LogError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Invalid JSON, or a log this version cannot understand. serde_json
+    /// reports the line and column where possible.
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A database of the SQLite backend that cannot be opened or queried.
+    Database {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            LogError::Parse { path, source } if source.line() > 0 => write!(
+                f,
+                "cannot parse {} (line {}, column {}): {}",
+                path.display(),
+                source.line(),
+                source.column(),
+                parse_error_message(source)
+            ),
+            LogError::Parse { path, source } => {
+                write!(f, "cannot parse {}: {}", path.display(), source)
+            }
+            LogError::Database { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LogError::Io { source, .. } => Some(source),
+            LogError::Parse { source, .. } => Some(source),
+            LogError::Database { source, .. } => Some(source),
+        }
+    }
+}
+
+/// The message of a serde_json error without the position it appends.
+fn parse_error_message(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    let position = format!(" at line {} column {}", err.line(), err.column());
+    message
+        .strip_suffix(&position)
+        .unwrap_or(&message)
+        .to_string()
+}
+
+/// Where the log of `version` is kept, for messages.
+fn log_path(ctx: &Context, version: &str) -> PathBuf {
+    ctx.store.location(ctx, version)
+}
+
+/// Where the project logs are kept. Commands reach the logs only through the
+/// context's store, so they behave the same however it keeps them.
+trait LogStore {
+    /// Versions that have a log, in no particular order.
+    fn versions(&self, ctx: &Context) -> Vec<String>;
+    fn exists(&self, ctx: &Context, version: &str) -> bool;
+    /// Loads the log of `version`, or an empty one if it has none yet.
+    fn load(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError>;
+    fn save(&self, ctx: &Context, log: &ProjectLog) -> io::Result<()>;
+    fn remove(&self, ctx: &Context, version: &str) -> io::Result<()>;
+    /// Takes the log of `version` out of use while keeping its contents
+    /// under `label`, returning where they went.
+    fn set_aside(&self, ctx: &Context, version: &str, label: &str) -> io::Result<PathBuf>;
+    fn location(&self, ctx: &Context, version: &str) -> PathBuf;
+    /// Rewrites the stored logs in the canonical formatting, returning the
+    /// files that were not formatted that way yet.
+    fn format(&self, ctx: &Context) -> io::Result<Vec<PathBuf>>;
+    /// Drops anything kept from earlier loads, so that the next ones see
+    /// changes made by other processes.
+    fn reload(&self) {}
+}
+
+/// One `<version>.json` file per version in the data directory, each backed
+/// up before it is overwritten or removed.
+struct JsonDirStore;
+
+impl LogStore for JsonDirStore {
+    fn versions(&self, ctx: &Context) -> Vec<String> {
+        let mut versions = Vec::new();
+        if let Ok(entries) = fs::read_dir(&ctx.data_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json")
+                    && entry.file_name() != DISCOVERY_CACHE_FILE
+                    && entry.file_name() != SINGLE_LOG_FILE
+                {
+                    if let Some(stem) = path.file_stem() {
+                        versions.push(stem.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        versions
+    }
+
+    fn exists(&self, ctx: &Context, version: &str) -> bool {
+        self.location(ctx, version).exists()
+    }
+
+    fn load(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+        Ok(read_json_file(ctx, &self.location(ctx, version))?
+            .unwrap_or_else(|| ProjectLog::empty(version)))
+    }
+
+    fn save(&self, ctx: &Context, log: &ProjectLog) -> io::Result<()> {
+        fs::create_dir_all(&ctx.data_dir)?;
+        self.backup(ctx, &log.version)?;
+        let mut log = log.clone();
+        sort_projects(&mut log);
+        let data = canonical_json(&log)?;
+        write_atomic(&self.location(ctx, &log.version), data.as_bytes())
+    }
+
+    fn remove(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        self.backup(ctx, version)?;
+        fs::remove_file(self.location(ctx, version))
+    }
+
+    fn set_aside(&self, ctx: &Context, version: &str, label: &str) -> io::Result<PathBuf> {
+        let path = self.location(ctx, version);
+        let aside = set_aside_path(ctx, version, label);
+        fs::rename(&path, &aside)?;
+        Ok(aside)
+    }
+
+    fn location(&self, ctx: &Context, version: &str) -> PathBuf {
+        ctx.data_dir.join(format!("{}.json", version))
+    }
+
+    fn format(&self, ctx: &Context) -> io::Result<Vec<PathBuf>> {
+        let mut versions = self.versions(ctx);
+        versions.sort_by_key(|version| version_sort_key(version));
+        let mut changed = Vec::new();
+        for version in versions {
+            let path = self.location(ctx, &version);
+            if format_log_file(ctx, &path, sort_projects)? {
+                changed.push(path);
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl JsonDirStore {
+    fn backup(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        let path = self.location(ctx, version);
+        if !path.exists() {
+            return Ok(());
+        }
+        backup_log(ctx, version, || fs::read(&path))
+    }
+}
+
+/// Where every store keeps the log of `version` once it is set aside under
+/// `label`.
+fn set_aside_path(ctx: &Context, version: &str, label: &str) -> PathBuf {
+    ctx.data_dir.join(format!("{}.json.{}", version, label))
+}
+
+/// The document of the single-file layout.
+const SINGLE_LOG_FILE: &str = "pymanager.json";
+
+#[derive(Serialize, Deserialize, Default)]
+struct SingleLogDocument {
+    logs: Vec<ProjectLog>,
+}
+
+/// Every version's log as a section of one `pymanager.json`, which is
+/// easier to sync with dotfiles. Backups are still taken per version, so
+/// `restore` works the same as with separate files.
+struct SingleFileStore;
+
+impl SingleFileStore {
+    fn path(&self, ctx: &Context) -> PathBuf {
+        ctx.data_dir.join(SINGLE_LOG_FILE)
+    }
+
+    fn read(&self, ctx: &Context) -> Result<SingleLogDocument, LogError> {
+        Ok(read_json_file(ctx, &self.path(ctx))?.unwrap_or_default())
+    }
+
+    /// Reads the document for a change; a document that cannot be parsed is
+    /// never overwritten.
+    fn read_for_update(&self, ctx: &Context) -> io::Result<SingleLogDocument> {
+        self.read(ctx).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cannot read {}: {}", self.path(ctx).display(), err),
+            )
+        })
+    }
+
+    /// Writes `document` back, removing the file once no logs are left.
+    fn write(&self, ctx: &Context, document: &mut SingleLogDocument) -> io::Result<()> {
+        if document.logs.is_empty() {
+            return match fs::remove_file(self.path(ctx)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(&ctx.data_dir)?;
+        sort_document(document);
+        let data = canonical_json(document)?;
+        write_atomic(&self.path(ctx), data.as_bytes())
+    }
+
+    /// Backs up the section of `version` and takes it out of `document`.
+    fn take(
+        &self,
+        ctx: &Context,
+        document: &mut SingleLogDocument,
+        version: &str,
+    ) -> io::Result<Option<ProjectLog>> {
+        let Some(index) = document.logs.iter().position(|log| log.version == version) else {
+            return Ok(None);
+        };
+        let data = canonical_json(&document.logs[index])?;
+        backup_log(ctx, version, || Ok(data.into_bytes()))?;
+        Ok(Some(document.logs.remove(index)))
+    }
+}
+
+impl LogStore for SingleFileStore {
+    fn versions(&self, ctx: &Context) -> Vec<String> {
+        match self.read(ctx) {
+            Ok(document) => document.logs.into_iter().map(|log| log.version).collect(),
+            Err(err) => {
+                ctx.skip_unreadable(&err);
+                Vec::new()
+            }
+        }
+    }
+
+    fn exists(&self, ctx: &Context, version: &str) -> bool {
+        self.versions(ctx).iter().any(|logged| logged == version)
+    }
+
+    fn load(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+        Ok(self
+            .read(ctx)?
+            .logs
+            .into_iter()
+            .find(|log| log.version == version)
+            .unwrap_or_else(|| ProjectLog::empty(version)))
+    }
+
+    fn save(&self, ctx: &Context, log: &ProjectLog) -> io::Result<()> {
+        let mut document = self.read_for_update(ctx)?;
+        self.take(ctx, &mut document, &log.version)?;
+        document.logs.push(log.clone());
+        self.write(ctx, &mut document)
+    }
+
+    fn remove(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        let mut document = self.read_for_update(ctx)?;
+        if self.take(ctx, &mut document, version)?.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no log for Python version {}", version),
+            ));
+        }
+        self.write(ctx, &mut document)
+    }
+
+    /// The section moves out to a `<version>.json.<label>` file of its own,
+    /// where it would be kept with separate files.
+    fn set_aside(&self, ctx: &Context, version: &str, label: &str) -> io::Result<PathBuf> {
+        let mut document = self.read_for_update(ctx)?;
+        let Some(index) = document.logs.iter().position(|log| log.version == version) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no log for Python version {}", version),
+            ));
+        };
+        let aside = set_aside_path(ctx, version, label);
+        write_atomic(&aside, canonical_json(&document.logs[index])?.as_bytes())?;
+        document.logs.remove(index);
+        self.write(ctx, &mut document)?;
+        Ok(aside)
+    }
+
+    fn location(&self, ctx: &Context, _version: &str) -> PathBuf {
+        self.path(ctx)
+    }
+
+    fn format(&self, ctx: &Context) -> io::Result<Vec<PathBuf>> {
+        let path = self.path(ctx);
+        if path.exists() && format_log_file(ctx, &path, sort_document)? {
+            return Ok(vec![path]);
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// The database of the SQLite backend.
+const SQLITE_FILE: &str = "pymanager.db";
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS versions (
+        name TEXT PRIMARY KEY,
+        schema_version INTEGER NOT NULL,
+        extra TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS projects (
+        version TEXT NOT NULL REFERENCES versions(name),
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        last_accessed INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (version, position)
+    );
+    CREATE INDEX IF NOT EXISTS projects_by_name ON projects(name);
+";
+
+/// Every version's log in one SQLite database, with a row per version and
+/// a row per project. Each project row keeps the project's JSON, so fields
+/// this version does not know survive, and loading goes through the same
+/// migrations as the JSON files. Backups are JSON, so `restore` works the
+/// same as with the other backend.
+struct SqliteStore;
+
+impl SqliteStore {
+    fn path(&self, ctx: &Context) -> PathBuf {
+        ctx.data_dir.join(SQLITE_FILE)
+    }
+
+    /// Opens the database for reading, or `None` if it was never created.
+    fn open(&self, ctx: &Context) -> Result<Option<rusqlite::Connection>, LogError> {
+        let path = self.path(ctx);
+        if !path.exists() {
+            return Ok(None);
+        }
+        rusqlite::Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map(Some)
+            .map_err(|source| LogError::Database { path, source })
+    }
+
+    /// Opens the database for a change, creating it if needed.
+    fn open_for_update(&self, ctx: &Context) -> io::Result<rusqlite::Connection> {
+        fs::create_dir_all(&ctx.data_dir)?;
+        let conn = rusqlite::Connection::open(self.path(ctx)).map_err(io::Error::other)?;
+        conn.execute_batch(SQLITE_SCHEMA)
+            .map_err(io::Error::other)?;
+        Ok(conn)
+    }
+
+    fn read_versions(&self, ctx: &Context) -> Result<Vec<String>, LogError> {
+        let Some(conn) = self.open(ctx)? else {
+            return Ok(Vec::new());
+        };
+        let database = |source| LogError::Database {
+            path: self.path(ctx),
+            source,
+        };
+        let mut statement = conn
+            .prepare("SELECT name FROM versions")
+            .map_err(database)?;
+        let versions = statement
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(database)?;
+        Ok(versions)
+    }
+
+    /// Reads the log of `version` as stored, before any migration.
+    fn read(
+        conn: &rusqlite::Connection,
+        version: &str,
+    ) -> rusqlite::Result<Option<serde_json::Value>> {
+        use rusqlite::OptionalExtension;
+        let row: Option<(u32, String)> = conn
+            .query_row(
+                "SELECT schema_version, extra FROM versions WHERE name = ?1",
+                [version],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((schema_version, extra)) = row else {
+            return Ok(None);
+        };
+        let mut statement =
+            conn.prepare("SELECT data FROM projects WHERE version = ?1 ORDER BY position")?;
+        let projects = statement
+            .query_map([version], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut log = serde_json::Map::new();
+        log.insert("schema_version".into(), schema_version.into());
+        log.insert("version".into(), version.into());
+        // Both columns are only ever written by `write`, from valid JSON.
+        log.insert(
+            "projects".into(),
+            projects
+                .iter()
+                .map(|data| serde_json::from_str::<serde_json::Value>(data).unwrap_or_default())
+                .collect(),
+        );
+        if let Ok(serde_json::Value::Object(extra)) = serde_json::from_str(&extra) {
+            log.extend(extra);
+        }
+        Ok(Some(serde_json::Value::Object(log)))
+    }
+
+    /// Backs up the stored log of `version` and deletes its rows, returning
+    /// whether there was one.
+    fn take(&self, ctx: &Context, tx: &rusqlite::Transaction, version: &str) -> io::Result<bool> {
+        let Some(current) = Self::read(tx, version).map_err(io::Error::other)? else {
+            return Ok(false);
+        };
+        let data = canonical_json(&current)?;
+        backup_log(ctx, version, || Ok(data.into_bytes()))?;
+        tx.execute("DELETE FROM projects WHERE version = ?1", [version])
+            .and_then(|_| tx.execute("DELETE FROM versions WHERE name = ?1", [version]))
+            .map_err(io::Error::other)?;
+        Ok(true)
+    }
+
+    fn write(tx: &rusqlite::Transaction, log: &ProjectLog) -> io::Result<()> {
+        let extra = serde_json::to_string(&log.extra)?;
+        tx.execute(
+            "INSERT INTO versions (name, schema_version, extra) VALUES (?1, ?2, ?3)",
+            rusqlite::params![log.version, log.schema_version, extra],
+        )
+        .map_err(io::Error::other)?;
+        for (position, project) in log.projects.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO projects (version, position, name, created_at, last_accessed, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    log.version,
+                    position as i64,
+                    project.name,
+                    project.created_at as i64,
+                    project.last_accessed as i64,
+                    serde_json::to_string(project)?,
+                ],
+            )
+            .map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `change` in a transaction, committing only if it succeeds.
+    fn update<T>(
+        &self,
+        ctx: &Context,
+        change: impl FnOnce(&rusqlite::Transaction) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut conn = self.open_for_update(ctx)?;
+        let tx = conn.transaction().map_err(io::Error::other)?;
+        let result = change(&tx)?;
+        tx.commit().map_err(io::Error::other)?;
+        Ok(result)
+    }
+}
+
+impl LogStore for SqliteStore {
+    fn versions(&self, ctx: &Context) -> Vec<String> {
+        self.read_versions(ctx).unwrap_or_else(|err| {
+            ctx.skip_unreadable(&err);
+            Vec::new()
+        })
+    }
+
+    fn exists(&self, ctx: &Context, version: &str) -> bool {
+        self.versions(ctx).iter().any(|logged| logged == version)
+    }
+
+    fn load(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+        let path = self.path(ctx);
+        let Some(conn) = self.open(ctx)? else {
+            return Ok(ProjectLog::empty(version));
+        };
+        match Self::read(&conn, version) {
+            Ok(Some(log)) => {
+                serde_json::from_value(log).map_err(|source| LogError::Parse { path, source })
+            }
+            Ok(None) => Ok(ProjectLog::empty(version)),
+            Err(source) => Err(LogError::Database { path, source }),
+        }
+    }
+
+    fn save(&self, ctx: &Context, log: &ProjectLog) -> io::Result<()> {
+        let mut log = log.clone();
+        sort_projects(&mut log);
+        self.update(ctx, |tx| {
+            self.take(ctx, tx, &log.version)?;
+            Self::write(tx, &log)
+        })
+    }
+
+    fn remove(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        if !self.update(ctx, |tx| self.take(ctx, tx, version))? {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no log for Python version {}", version),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The log moves out to a `<version>.json.<label>` file, where it would
+    /// be kept with the JSON backend.
+    fn set_aside(&self, ctx: &Context, version: &str, label: &str) -> io::Result<PathBuf> {
+        self.update(ctx, |tx| {
+            let Some(current) = Self::read(tx, version).map_err(io::Error::other)? else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no log for Python version {}", version),
+                ));
+            };
+            let aside = set_aside_path(ctx, version, label);
+            write_atomic(&aside, canonical_json(&current)?.as_bytes())?;
+            tx.execute("DELETE FROM projects WHERE version = ?1", [version])
+                .and_then(|_| tx.execute("DELETE FROM versions WHERE name = ?1", [version]))
+                .map_err(io::Error::other)?;
+            Ok(aside)
+        })
+    }
+
+    fn location(&self, ctx: &Context, _version: &str) -> PathBuf {
+        self.path(ctx)
+    }
+
+    /// There is no text to format; the database is always written the same
+    /// way.
+    fn format(&self, _ctx: &Context) -> io::Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Keeps every log loaded through `inner` for the rest of the invocation,
+/// so that commands reading the same log several times parse it once. A
+/// save only reaches `inner` when the log differs from the cached copy.
+struct CachedStore {
+    inner: Box<dyn LogStore>,
+    logs: RefCell<BTreeMap<String, ProjectLog>>,
+    versions: RefCell<Option<Vec<String>>>,
+}
+
+impl CachedStore {
+    fn new(inner: Box<dyn LogStore>) -> Self {
+        CachedStore {
+            inner,
+            logs: RefCell::new(BTreeMap::new()),
+            versions: RefCell::new(None),
+        }
+    }
+
+    fn forget_version(&self, version: &str) {
+        self.logs.borrow_mut().remove(version);
+        self.versions.take();
+    }
+}
+
+impl LogStore for CachedStore {
+    fn versions(&self, ctx: &Context) -> Vec<String> {
+        if let Some(versions) = &*self.versions.borrow() {
+            return versions.clone();
+        }
+        let versions = self.inner.versions(ctx);
+        *self.versions.borrow_mut() = Some(versions.clone());
+        versions
+    }
+
+    fn exists(&self, ctx: &Context, version: &str) -> bool {
+        self.inner.exists(ctx, version)
+    }
+
+    fn load(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+        if let Some(log) = self.logs.borrow().get(version) {
+            return Ok(log.clone());
+        }
+        let log = self.inner.load(ctx, version)?;
+        self.logs
+            .borrow_mut()
+            .insert(version.to_string(), log.clone());
+        Ok(log)
+    }
+
+    fn save(&self, ctx: &Context, log: &ProjectLog) -> io::Result<()> {
+        let mut log = log.clone();
+        sort_projects(&mut log);
+        if self.logs.borrow().get(&log.version) == Some(&log) {
+            ctx.debug(&format!("Python {} is unchanged, not writing it", log.version));
+            return Ok(());
+        }
+        self.forget_version(&log.version);
+        self.inner.save(ctx, &log)?;
+        self.logs.borrow_mut().insert(log.version.clone(), log);
+        Ok(())
+    }
+
+    fn remove(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        self.forget_version(version);
+        self.inner.remove(ctx, version)
+    }
+
+    fn set_aside(&self, ctx: &Context, version: &str, label: &str) -> io::Result<PathBuf> {
+        self.forget_version(version);
+        self.inner.set_aside(ctx, version, label)
+    }
+
+    fn location(&self, ctx: &Context, version: &str) -> PathBuf {
+        self.inner.location(ctx, version)
+    }
+
+    fn format(&self, ctx: &Context) -> io::Result<Vec<PathBuf>> {
+        self.inner.format(ctx)
+    }
+
+    fn reload(&self) {
+        self.logs.borrow_mut().clear();
+        self.versions.take();
+        self.inner.reload();
+    }
+}
+
+/// The user's store layered over the read-only, system-wide logs of
+/// `Context::system_dir`. Loads merge both, with a user entry shadowing the
+/// system one of the same name; every write goes to the user's store, which
+/// only keeps the system entries that were changed.
+struct LayeredStore {
+    user: Box<dyn LogStore>,
+}
+
+impl LayeredStore {
+    fn system_log(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+        load_system_log(ctx, version).map(|log| log.unwrap_or_else(|| ProjectLog::empty(version)))
+    }
+
+    /// Refuses to drop the log of `version` while it has system-wide
+    /// projects, as they would come straight back.
+    fn check_removable(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        let system = self
+            .system_log(ctx, version)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        if system.projects.is_empty() {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "Python version {} has system-wide projects in {}, which cannot be removed",
+                version,
+                system_log_path(ctx, version).display()
+            ),
+        ))
+    }
+}
+
+impl LogStore for LayeredStore {
+    fn versions(&self, ctx: &Context) -> Vec<String> {
+        let mut versions = self.user.versions(ctx);
+        for version in system_versions(ctx) {
+            if !versions.contains(&version) {
+                versions.push(version);
+            }
+        }
+        versions
+    }
+
+    fn exists(&self, ctx: &Context, version: &str) -> bool {
+        self.user.exists(ctx, version) || system_log_path(ctx, version).exists()
+    }
+
+    fn load(&self, ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+        let system = self.system_log(ctx, version)?;
+        Ok(merge_layers(&system, &self.user.load(ctx, version)?))
+    }
+
+    fn save(&self, ctx: &Context, log: &ProjectLog) -> io::Result<()> {
+        let system = self
+            .system_log(ctx, &log.version)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let user = user_layer(&system, log).map_err(|name| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "project '{}' is registered system-wide in {} and cannot be removed",
+                    name,
+                    system_log_path(ctx, &log.version).display()
+                ),
+            )
+        })?;
+        self.user.save(ctx, &user)
+    }
+
+    fn remove(&self, ctx: &Context, version: &str) -> io::Result<()> {
+        self.check_removable(ctx, version)?;
+        self.user.remove(ctx, version)
+    }
+
+    fn set_aside(&self, ctx: &Context, version: &str, label: &str) -> io::Result<PathBuf> {
+        self.check_removable(ctx, version)?;
+        self.user.set_aside(ctx, version, label)
+    }
+
+    fn location(&self, ctx: &Context, version: &str) -> PathBuf {
+        self.user.location(ctx, version)
+    }
+
+    fn format(&self, ctx: &Context) -> io::Result<Vec<PathBuf>> {
+        self.user.format(ctx)
+    }
+
+    fn reload(&self) {
+        self.user.reload();
+    }
+}
+
+/// Where the system-wide log of `version` would be; system logs always use
+/// the per-version layout.
+fn system_log_path(ctx: &Context, version: &str) -> PathBuf {
+    ctx.system_dir
+        .as_deref()
+        .unwrap_or(Path::new(""))
+        .join(format!("{}.json", version))
+}
+
+/// Versions with a system-wide log.
+fn system_versions(ctx: &Context) -> Vec<String> {
+    let Some(dir) = &ctx.system_dir else {
+        return Vec::new();
+    };
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json"
+                || entry.file_name() == DISCOVERY_CACHE_FILE
+                || entry.file_name() == SINGLE_LOG_FILE
+            {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Loads the system-wide log of `version`, or `None` if there is none.
+fn load_system_log(ctx: &Context, version: &str) -> Result<Option<ProjectLog>, LogError> {
+    if ctx.system_dir.is_none() {
+        return Ok(None);
+    }
+    read_json_file(ctx, &system_log_path(ctx, version))
+}
+
+/// The log seen with `user` layered over `system`: every user project, plus
+/// the system projects no user project shadows by name.
+fn merge_layers(system: &ProjectLog, user: &ProjectLog) -> ProjectLog {
+    let mut merged = user.clone();
+    for project in &system.projects {
+        if !user.projects.iter().any(|own| own.name == project.name) {
+            merged.projects.push(project.clone());
+        }
+    }
+    sort_projects(&mut merged);
+    merged
+}
+
+/// The part of `merged` the user's log has to keep so that layering it over
+/// `system` gives `merged` back: system projects that are unchanged are left
+/// out. A system project missing from `merged` cannot be expressed, and its
+/// name is returned as the error.
+fn user_layer(system: &ProjectLog, merged: &ProjectLog) -> Result<ProjectLog, String> {
+    if let Some(missing) = system
+        .projects
+        .iter()
+        .find(|project| !merged.projects.iter().any(|own| own.name == project.name))
+    {
+        return Err(missing.name.clone());
+    }
+    let mut user = merged.clone();
+    user.projects.retain(|project| {
+        system
+            .projects
+            .iter()
+            .find(|inherited| inherited.name == project.name)
+            .is_none_or(|inherited| !changed_fields(inherited, project).is_empty())
+    });
+    Ok(user)
+}
+
+/// Which layer a project of a merged log comes from.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Origin {
+    User,
+    System,
+    /// A user entry hiding a system-wide one of the same name.
+    Shadowing,
+}
+
+impl Origin {
+    fn name(self) -> &'static str {
+        match self {
+            Origin::User => "user",
+            Origin::System => "system",
+            Origin::Shadowing => "user, shadowing system",
+        }
+    }
+}
+
+/// The origin of each project of `log`, which was loaded through the
+/// store, by name.
+fn project_origins(ctx: &Context, log: &ProjectLog) -> BTreeMap<String, Origin> {
+    let system = match load_system_log(ctx, &log.version) {
+        Ok(system) => system.unwrap_or_else(|| ProjectLog::empty(&log.version)),
+        Err(err) => {
+            ctx.skip_unreadable(&err);
+            ProjectLog::empty(&log.version)
+        }
+    };
+    log.projects
+        .iter()
+        .map(|project| {
+            let origin = match system
+                .projects
+                .iter()
+                .find(|inherited| inherited.name == project.name)
+            {
+                None => Origin::User,
+                Some(inherited) if changed_fields(inherited, project).is_empty() => {
+                    Origin::System
+                }
+                Some(_) => Origin::Shadowing,
+            };
+            (project.name.clone(), origin)
+        })
+        .collect()
+}
+
+fn sort_document(document: &mut SingleLogDocument) {
+    document
+        .logs
+        .sort_by_key(|log| version_sort_key(&log.version));
+    document.logs.iter_mut().for_each(sort_projects);
+}
+
+/// Projects are written in name order so that adding or touching one
+/// changes as few lines as possible.
+fn sort_projects(log: &mut ProjectLog) {
+    log.projects.sort_by(|a, b| a.name.cmp(&b.name));
+}
+
+/// The formatting of everything pymanager writes to the data directory:
+/// two-space indentation and a trailing newline, friendly to `git diff`.
+fn canonical_json<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    let mut data = serde_json::to_string_pretty(value)?;
+    data.push('\n');
+    Ok(data)
+}
+
+/// Rewrites the log file at `path` in the canonical formatting, after
+/// `canonicalize` has put its contents in order. Returns whether the file
+/// changed; one that cannot be parsed is left alone.
+fn format_log_file<T: Serialize + serde::de::DeserializeOwned>(
+    ctx: &Context,
+    path: &Path,
+    canonicalize: impl FnOnce(&mut T),
+) -> io::Result<bool> {
+    let data = fs::read_to_string(path)?;
+    let mut value: T = match serde_json::from_str(&data) {
+        Ok(value) => value,
+        Err(source) => {
+            ctx.skip_unreadable(&LogError::Parse {
+                path: path.to_path_buf(),
+                source,
+            });
+            return Ok(false);
+        }
+    };
+    canonicalize(&mut value);
+    let formatted = canonical_json(&value)?;
+    if formatted == data {
+        return Ok(false);
+    }
+    if !ctx.dry_run {
+        write_atomic(path, formatted.as_bytes())?;
+    }
+    Ok(true)
+}
+
+/// Reads and parses the JSON file at `path`, or `None` if there is none.
+fn read_json_file<T: serde::de::DeserializeOwned>(
+    ctx: &Context,
+    path: &Path,
+) -> Result<Option<T>, LogError> {
+    let temp = temp_path(path);
+    if path.exists() {
+        // A leftover temporary file is an interrupted save; the file itself
+        // was never touched, so it wins.
+        if temp.exists() {
+            ctx.debug(&format!("ignoring interrupted write {}", temp.display()));
+        }
+        let data = fs::read_to_string(path).map_err(|source| LogError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        return serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|source| LogError::Parse {
+                path: path.to_path_buf(),
+                source,
+            });
+    }
+    // A save that was flushed but not yet renamed when it was cut short
+    // left the only copy of the new contents behind.
+    if let Some(value) = fs::read_to_string(&temp)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+    {
+        eprintln!(
+            "warning: recovered {} from {}",
+            path.display(),
+            temp.display()
+        );
+        return Ok(Some(value));
+    }
+    Ok(None)
+}
+
+/// The file a log is written to before it is renamed over `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Replaces `path` with `data` so that readers see either the old or the new
+/// contents, never a partial write: the data goes to a temporary file in the
+/// same directory, is flushed to disk and then renamed into place.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp, path)
+}
+
+fn try_load_project_log(ctx: &Context, version: &str) -> Result<ProjectLog, LogError> {
+    ctx.store.load(ctx, version)
+}
+
+fn save_project_log(ctx: &Context, log: &ProjectLog) -> io::Result<()> {
+    ctx.store.save(ctx, log)
+}
+
+/// Versions that have a log, whether or not the interpreter is still
+/// installed.
+fn logged_versions(ctx: &Context) -> Vec<String> {
+    let mut versions = ctx.store.versions(ctx);
+    versions.sort_by_key(|version| version_sort_key(version));
+    versions
+}
+
+fn log_exists(ctx: &Context, version: &str) -> bool {
+    ctx.store.exists(ctx, version)
+}
+
+/// Backups are named `<version>.json.bak.<timestamp>` in this format, which
+/// sorts in creation order, with `.gz` appended once compressed.
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// The backup of `version` taken at `timestamp`: the compressed file if
+/// there is one, and otherwise the plain file, as written before backups
+/// were compressed or where `gzip` is missing.
+fn backup_path(ctx: &Context, version: &str, timestamp: &str) -> PathBuf {
+    let path = ctx
+        .data_dir
+        .join(format!("{}.json.bak.{}", version, timestamp));
+    let compressed = gzip_path(&path);
+    if compressed.exists() {
+        compressed
+    } else {
+        path
+    }
+}
+
+/// Level at which backups and rotated journals are compressed. They are
+/// rarely read again, so a quick save matters more than a small file.
+const GZIP_LEVEL: &str = "-1";
+
+/// Runs the `gzip` program with `args` as a filter over `data`.
+fn run_gzip(args: &[&str], data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut child = Command::new("gzip")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Feed the input on its own thread so that gzip cannot stall on a full
+    // output pipe while we are still writing.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = data.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    let written = writer
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("gzip input thread panicked")));
+    if !output.status.success() {
+        return Err(io::Error::other(format!("gzip exited with {}", output.status)));
+    }
+    written?;
+    Ok(output.stdout)
+}
+
+/// `path` with `.gz` appended.
+fn gzip_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".gz");
+    path.with_file_name(name)
+}
+
+/// Reads `path`, decompressing it when its name ends in `.gz`.
+fn read_maybe_compressed(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        return run_gzip(&["-d", "-c"], &data);
+    }
+    Ok(data)
+}
+
+/// Writes `data` compressed to `path` with `.gz` appended, or as it is to
+/// `path` when `gzip` cannot be run. Returns the file written.
+fn write_compressed(ctx: &Context, path: &Path, data: &[u8]) -> io::Result<PathBuf> {
+    match run_gzip(&["-c", "-n", GZIP_LEVEL], data) {
+        Ok(compressed) => {
+            let compressed_path = gzip_path(path);
+            fs::write(&compressed_path, compressed)?;
+            Ok(compressed_path)
+        }
+        Err(err) => {
+            ctx.debug(&format!("not compressing {}: {}", path.display(), err));
+            fs::write(path, data)?;
+            Ok(path.to_path_buf())
+        }
+    }
+}
+
+/// Timestamps of the automatic backups of `version`, oldest first.
+fn list_backups(ctx: &Context, version: &str) -> Vec<String> {
+    let prefix = format!("{}.json.bak.", version);
+    let mut timestamps: Vec<String> = fs::read_dir(&ctx.data_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let timestamp = name.strip_prefix(&prefix)?;
+            let timestamp = timestamp.strip_suffix(".gz").unwrap_or(timestamp);
+            NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT).ok()?;
+            Some(timestamp.to_string())
+        })
+        .collect();
+    timestamps.sort();
+    timestamps.dedup();
+    timestamps
+}
+
+/// Stores the current log of `version`, as read by `data`, as a compressed
+/// timestamped backup before it is overwritten, then removes all but the
+/// newest `backup_count` backups. A backup already taken this second is
+/// kept, as it holds the older state.
+fn backup_log(
+    ctx: &Context,
+    version: &str,
+    data: impl FnOnce() -> io::Result<Vec<u8>>,
+) -> io::Result<()> {
+    if ctx.backup_count == 0 {
+        return Ok(());
+    }
+    let timestamp = Local::now().format(BACKUP_TIMESTAMP_FORMAT).to_string();
+    let mut backup = backup_path(ctx, version, &timestamp);
+    if !backup.exists() {
+        backup = data()
+            .and_then(|data| write_compressed(ctx, &backup, &data))
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("could not back up to {}: {}", backup.display(), err),
+                )
+            })?;
+        ctx.debug(&format!(
+            "backed up Python {} to {}",
+            version,
+            backup.display()
+        ));
+    }
+    let backups = list_backups(ctx, version);
+    let excess = backups.len().saturating_sub(ctx.backup_count);
+    for timestamp in &backups[..excess] {
+        let old = backup_path(ctx, version, timestamp);
+        match fs::remove_file(&old) {
+            Ok(()) => ctx.debug(&format!("removed old backup {}", old.display())),
+            Err(err) => eprintln!("warning: could not remove {}: {}", old.display(), err),
+        }
+    }
+    Ok(())
+}
+
+/// Loads every log in the data directory, warning about and skipping any
+/// that cannot be read or parsed unless `--strict` was given.
+fn load_all_logs(ctx: &Context) -> Vec<ProjectLog> {
+    let mut logs = Vec::new();
+    for version in logged_versions(ctx) {
+        match try_load_project_log(ctx, &version) {
+            Ok(log) => logs.push(log),
+            Err(err) => ctx.skip_unreadable(&err),
+        }
+    }
+    logs
+}
+
+/// The journal of changes, one JSON object per line.
+const JOURNAL_FILE: &str = "journal.ndjson";
+
+/// Size past which the journal is rotated to `journal.ndjson.1.gz`,
+/// replacing the previous rotation.
+const JOURNAL_MAX_BYTES: u64 = 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JournalAction {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    timestamp: u64,
+    command: String,
+    version: String,
+    project: String,
+    action: JournalAction,
+    /// The fields of the project that changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+}
+
+/// The subcommand path of `matches`, such as `alias add`.
+fn command_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
+/// Fields whose value differs between two versions of a project.
+fn changed_fields(before: &Project, after: &Project) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(before)), Ok(serde_json::Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return Vec::new();
+    };
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect()
+}
+
+/// Journals every project added, removed or changed between two states of
+/// a log.
+fn journal_changes(ctx: &Context, before: &ProjectLog, after: &ProjectLog) {
+    let timestamp = current_timestamp();
+    let mut entries = Vec::new();
+    for project in &after.projects {
+        match before.projects.iter().find(|old| old.name == project.name) {
+            None => entries.push(JournalEntry {
+                timestamp,
+                command: ctx.command.clone(),
+                version: after.version.clone(),
+                project: project.name.clone(),
+                action: JournalAction::Added,
+                fields: Vec::new(),
+                before: None,
+                after: Some(describe_project(project, false)),
+            }),
+            Some(old) => {
+                let fields = changed_fields(old, project);
+                if !fields.is_empty() {
+                    entries.push(JournalEntry {
+                        timestamp,
+                        command: ctx.command.clone(),
+                        version: after.version.clone(),
+                        project: project.name.clone(),
+                        action: JournalAction::Changed,
+                        fields,
+                        before: Some(describe_project(old, false)),
+                        after: Some(describe_project(project, false)),
+                    });
+                }
+            }
+        }
+    }
+    for old in &before.projects {
+        if !after
+            .projects
+            .iter()
+            .any(|project| project.name == old.name)
+        {
+            entries.push(JournalEntry {
+                timestamp,
+                command: ctx.command.clone(),
+                version: before.version.clone(),
+                project: old.name.clone(),
+                action: JournalAction::Removed,
+                fields: Vec::new(),
+                before: Some(describe_project(old, false)),
+                after: None,
+            });
+        }
+    }
+    if let Err(err) = append_journal(ctx, &entries) {
+        eprintln!(
+            "warning: could not write the journal {}: {}",
+            ctx.data_dir.join(JOURNAL_FILE).display(),
+            err
+        );
+    }
+}
+
+fn rotated_journal_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".1");
+    path.with_file_name(name)
+}
+
+fn append_journal(ctx: &Context, entries: &[JournalEntry]) -> io::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let path = ctx.data_dir.join(JOURNAL_FILE);
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() >= JOURNAL_MAX_BYTES) {
+        let rotated = rotated_journal_path(&path);
+        for old in [gzip_path(&rotated), rotated.clone()] {
+            match fs::remove_file(&old) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        write_compressed(ctx, &rotated, &fs::read(&path)?)?;
+        fs::remove_file(&path)?;
+    }
+    let mut data = String::new();
+    for entry in entries {
+        data.push_str(&serde_json::to_string(entry)?);
+        data.push('\n');
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    file.write_all(data.as_bytes())
+}
+
+/// Every journaled change, oldest first, from the rotated journal, which
+/// may be compressed, and the current one.
+fn read_journal(ctx: &Context) -> Vec<JournalEntry> {
+    let path = ctx.data_dir.join(JOURNAL_FILE);
+    let rotated = rotated_journal_path(&path);
+    let mut entries = Vec::new();
+    for file in [gzip_path(&rotated), rotated, path] {
+        let data = match read_maybe_compressed(&file) {
+            Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                eprintln!("warning: cannot read {}: {}", file.display(), err);
+                continue;
+            }
+        };
+        for (number, line) in data.lines().enumerate() {
+            match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => ctx.debug(&format!(
+                    "skipping line {} of {}: {}",
+                    number + 1,
+                    file.display(),
+                    err
+                )),
+            }
+        }
+    }
+    entries
+}
+
+fn show_journal(
+    ctx: &Context,
+    limit: usize,
+    project: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut entries = read_journal(ctx);
+    entries.retain(|entry| project.is_none_or(|name| entry.project == name));
+    if entries.is_empty() {
+        println!("No changes journaled.");
+        return Ok(());
+    }
+    for entry in &entries[entries.len().saturating_sub(limit)..] {
+        let action = match entry.action {
+            JournalAction::Added => "added".to_string(),
+            JournalAction::Removed => "removed".to_string(),
+            JournalAction::Changed => format!("changed {}", entry.fields.join(", ")),
+        };
+        println!(
+            "{}  {}  Python {}  {}: {}",
+            format_timestamp(entry.timestamp),
+            entry.command,
+            entry.version,
+            entry.project,
+            action
+        );
+    }
+    Ok(())
+}
+
+/// The checksums recorded by the last `integrity` run.
+const INTEGRITY_MANIFEST_FILE: &str = "integrity.manifest";
+
+#[derive(Serialize, Deserialize)]
+struct IntegrityManifest {
+    created_at: u64,
+    /// Checksum of each log file, by file name.
+    files: BTreeMap<String, String>,
+}
+
+/// 64-bit FNV-1a of `data`. It only has to notice files that changed, such
+/// as ones truncated by a sync conflict, so it need not be cryptographic.
+fn checksum(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("fnv1a64:{:016x}", hash)
+}
+
+/// Whether `path` holds logs, in either backend, rather than a cache.
+fn is_log_file(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default();
+    if name == SQLITE_FILE {
+        return true;
+    }
+    path.extension().is_some_and(|ext| ext == "json") && name != DISCOVERY_CACHE_FILE
+}
+
+/// The checksum of every log file in the data directory, by file name.
+fn log_file_checksums(ctx: &Context) -> io::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(&ctx.data_dir)?.flatten() {
+        let path = entry.path();
+        if !is_log_file(&path) {
+            continue;
+        }
+        files.insert(
+            entry.file_name().to_string_lossy().into_owned(),
+            checksum(&fs::read(&path)?),
+        );
+    }
+    Ok(files)
+}
+
+fn integrity(ctx: &Context, verify: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest_path = ctx.data_dir.join(INTEGRITY_MANIFEST_FILE);
+    let current = if ctx.data_dir.exists() {
+        log_file_checksums(ctx)?
+    } else {
+        BTreeMap::new()
+    };
+    let previous: Option<IntegrityManifest> = match fs::read_to_string(&manifest_path) {
+        Ok(data) => Some(
+            serde_json::from_str(&data)
+                .map_err(|err| format!("Cannot parse {}: {}", manifest_path.display(), err))?,
+        ),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(format!("Cannot read {}: {}", manifest_path.display(), err).into()),
+    };
+
+    let mut differences = 0;
+    match &previous {
+        Some(previous) => {
+            for (name, sum) in &current {
+                match previous.files.get(name) {
+                    None => println!("appeared     {}", name),
+                    Some(old) if old != sum => println!("changed      {}", name),
+                    Some(_) => continue,
+                }
+                differences += 1;
+            }
+            for name in previous.files.keys() {
+                if !current.contains_key(name) {
+                    println!("disappeared  {}", name);
+                    differences += 1;
+                }
+            }
+            if differences == 0 {
+                println!(
+                    "No changes to {} log file(s) since {}",
+                    current.len(),
+                    format_timestamp(previous.created_at)
+                );
+            }
+        }
+        None if verify => {
+            return Err(format!(
+                "No manifest at {}; record one with `pymanager integrity` first",
+                manifest_path.display()
+            )
+            .into())
+        }
+        None => {}
+    }
+
+    if verify {
+        if differences > 0 {
+            return Err(format!(
+                "{} difference(s) since {}",
+                differences,
+                format_timestamp(previous.map_or(0, |previous| previous.created_at))
+            )
+            .into());
+        }
+        return Ok(());
+    }
+    let manifest = IntegrityManifest {
+        created_at: current_timestamp(),
+        files: current,
+    };
+    if !ctx.dry_run {
+        fs::create_dir_all(&ctx.data_dir)?;
+        write_atomic(&manifest_path, canonical_json(&manifest)?.as_bytes())?;
+    }
+    ctx.report(&format!(
+        "Recorded the checksums of {} log file(s) in {}",
+        manifest.files.len(),
+        manifest_path.display()
+    ));
+    Ok(())
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    match Local.timestamp_opt(timestamp as i64, 0).single() {
+        Some(date) => date.format("%Y-%m-%d %H:%M").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+/// Shows `timestamp` as a local date and time, or as how long ago it was
+/// when `relative`.
+fn format_time(timestamp: u64, relative: bool) -> String {
+    if relative {
+        format_relative(timestamp, current_timestamp())
+    } else {
+        format_timestamp(timestamp)
+    }
+}
+
+fn format_relative(timestamp: u64, now: u64) -> String {
+    let elapsed = now.saturating_sub(timestamp);
+    let (amount, unit) = match elapsed {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (elapsed / 60, "minute"),
+        3_600..=86_399 => (elapsed / 3_600, "hour"),
+        86_400..=2_591_999 => (elapsed / 86_400, "day"),
+        2_592_000..=31_535_999 => (elapsed / 2_592_000, "month"),
+        _ => (elapsed / 31_536_000, "year"),
+    };
+    format!(
+        "{} {}{} ago",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
+
+/// Parses a point in time given as a unix timestamp (`1718000000`), an ISO
+/// date (`2024-06-01`, local midnight) or a duration before now (`30d`).
+fn parse_time_spec(spec: &str) -> Result<u64, String> {
+    let spec = spec.trim();
+    if let Ok(timestamp) = spec.parse::<u64>() {
+        return Ok(timestamp);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+        if let Some(local) = Local.from_local_datetime(&midnight).earliest() {
+            return Ok(local.timestamp().max(0) as u64);
+        }
+    }
+    let re = Regex::new(r"^(\d+)([smhdw])$").unwrap();
+    if let Some(caps) = re.captures(spec) {
+        let amount: u64 = caps[1]
+            .parse()
+            .map_err(|_| format!("'{}' is too large", spec))?;
+        let unit = match &caps[2] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => 7 * 24 * 60 * 60,
+        };
+        return Ok(current_timestamp().saturating_sub(amount.saturating_mul(unit)));
+    }
+    Err(format!(
+        "invalid time '{}': expected a unix timestamp, an ISO date like 2024-06-01 \
+         or a relative duration like 30d (units: s, m, h, d, w)",
+        spec
+    ))
+}
+
+/// Finds a project by name, falling back to its aliases when no project
+/// carries that exact name.
+fn find_project(log: &ProjectLog, name: &str) -> Option<usize> {
+    find_project_by_name(log, name).or_else(|| {
+        log.projects
+            .iter()
+            .position(|p| p.aliases.iter().any(|a| a == name))
+    })
+}
+
+fn find_project_by_name(log: &ProjectLog, name: &str) -> Option<usize> {
+    log.projects.iter().position(|p| p.name == name)
+}
+
+fn require_project(log: &ProjectLog, name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    find_project(log, name).ok_or_else(|| {
+        let mut message = format!(
+            "Project '{}' not found for Python version {}",
+            name, log.version
+        );
+        if let Some(closest) = closest_project_name(log, name) {
+            message.push_str(&format!(" (did you mean '{}'?)", closest));
+        }
+        message.into()
+    })
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Suggests the project whose name is closest to `name`, ignoring names that
+/// are too different to be a plausible typo.
+fn closest_project_name<'a>(log: &'a ProjectLog, name: &str) -> Option<&'a str> {
+    log.projects
+        .iter()
+        .map(|p| (edit_distance(&p.name, name), p.name.as_str()))
+        .filter(|(distance, candidate)| *distance <= candidate.len().max(name.len()) / 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn list_python_versions(
+    ctx: &Context,
+    options: &VersionListOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let VersionListOptions {
+        with_counts,
+        paths,
+        pip,
+        probe,
+        jobs,
+        include_venvs,
+        only_supported,
+        json,
+    } = *options;
+    let mut installs = get_python_versions(ctx);
+    let default = if paths {
+        resolve_on_path(ctx, "python3").map(|(_, resolved)| resolved)
+    } else {
+        None
+    };
+    if include_venvs {
+        installs.extend(venv_installs(ctx));
+        installs.sort_by_key(|install| (install.version, install.implementation));
+    }
+    if probe {
+        probe_installs(&mut installs, jobs as usize);
+    }
+    let today = Local::now().date_naive();
+    if only_supported {
+        installs.retain(|install| !is_eol_log(&install.log_key(), today));
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&installs)?);
+        return Ok(());
+    }
+    let versions = version_names(&installs);
+    if versions.is_empty() {
+        println!("No Python versions found.");
+    } else {
+        println!("Python versions found:");
+        for version in versions {
+            let matching: Vec<&PythonInstall> = installs
+                .iter()
+                .filter(|install| install.log_key() == version)
+                .collect();
+            let language_version = matching[0].version;
+            let mut line = language_version.to_string();
+            if probe {
+                let mut full_versions: Vec<String> = Vec::new();
+                for install in &matching {
+                    let full_version = install.full_version.unwrap_or(language_version).to_string();
+                    if !full_versions.contains(&full_version) {
+                        full_versions.push(full_version);
+                    }
+                }
+                line = full_versions.join(", ");
+            }
+            let pypy = matching[0].implementation == Implementation::PyPy;
+            if pypy {
+                line.push_str(" (pypy)");
+            }
+            for tag in matching.iter().filter_map(|install| install.source_tag()) {
+                line.push_str(&format!(" [{}]", tag));
+            }
+            if !pypy {
+                if let Some(status) = eol_status(language_version, today) {
+                    line.push_str(&format!(" ({})", status));
+                }
+            }
+            if with_counts {
+                // Only load the logs when asked so the default listing stays cheap.
+                let count = load_project_log(ctx, &version).projects.len();
+                line.push_str(&format!(" ({} projects)", count));
+            }
+            println!("{}", line);
+            if paths {
+                // One line per binary name; aliases are the symlinks that
+                // resolved to the install. `*` marks what `python3` runs.
+                for install in &matching {
+                    let full_version = install.full_version.unwrap_or(install.version).to_string();
+                    let marker = if Some(&install.path) == default.as_ref() {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    let mut line =
+                        format!("{} {:8}  {}", marker, full_version, install.path.display());
+                    if let Some(build) = &install.build {
+                        line.push_str(&format!("  [{}, {}]", build.architecture, build.compiler));
+                    }
+                    if pip {
+                        match pip_version(&install.path) {
+                            Ok(Some(version)) => line.push_str(&format!("  [pip {}]", version)),
+                            Ok(None) => line.push_str("  [no pip]"),
+                            Err(err) => line.push_str(&format!("  [pip unknown: {}]", err)),
+                        }
+                    }
+                    println!("{}", line);
+                    for alias in &install.aliases {
+                        println!(
+                            "  {:8}  {} -> {}",
+                            full_version,
+                            alias.display(),
+                            install.path.display()
+                        );
+                    }
+                }
+            }
+            // PyPy projects used to share the plain version's log.
+            let has_cpython = installs.iter().any(|install| {
+                install.implementation == Implementation::CPython
+                    && install.version == language_version
+            });
+            if pypy
+                && !has_cpython
+                && !log_exists(ctx, &version)
+                && log_exists(ctx, &language_version.to_string())
+            {
+                println!(
+                    "  note: move PyPy projects logged under {} with `pymanager merge-version {} {}`",
+                    language_version, language_version, version
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// How long `python -m pip --version` may take; pip imports a lot.
+const PIP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The version of pip the interpreter can import, or `None` when it has no
+/// pip.
+fn pip_version(interpreter: &Path) -> Result<Option<String>, String> {
+    let output = output_with_timeout(
+        Command::new(interpreter).args(["-m", "pip", "--version"]),
+        PIP_TIMEOUT,
+    )
+    .map_err(|err| err.to_string())?
+    .ok_or_else(|| format!("no answer within {} seconds", PIP_TIMEOUT.as_secs()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        // `pip 23.2.1 from /usr/lib/python3/dist-packages/pip (python 3.11)`
+        return stdout
+            .split_whitespace()
+            .nth(1)
+            .map(|version| Some(version.to_string()))
+            .ok_or_else(|| "unrecognised pip --version output".to_string());
+    }
+    if stderr.contains("No module named pip") {
+        Ok(None)
+    } else {
+        Err(stderr
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("pip failed")
+            .to_string())
+    }
+}
+
+fn check_pip(ctx: &Context, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let interpreter = python_interpreter(ctx, version)
+        .ok_or_else(|| format!("No interpreter found for Python version {}", version))?;
+    match pip_version(&interpreter)? {
+        Some(pip) => {
+            println!("pip {} ({})", pip, interpreter.display());
+            Ok(())
+        }
+        None => Err(format!("pip is not available for {}", interpreter.display()).into()),
+    }
+}
+
+/// Standard modules that distribution builds often leave out.
+const CHECKED_MODULES: [&str; 3] = ["ssl", "sqlite3", "lzma"];
+
+/// The outcome of one `check` step.
+#[derive(Serialize)]
+struct CheckResult {
+    name: String,
+    passed: bool,
+    detail: String,
+}
+
+/// Imports `module` with the interpreter, returning the error it printed
+/// when that fails.
+fn check_import(interpreter: &Path, module: &str) -> Result<(), String> {
+    let output = output_with_timeout(
+        Command::new(interpreter).args(["-c", &format!("import {}", module)]),
+        PROBE_TIMEOUT,
+    )
+    .map_err(|err| err.to_string())?
+    .ok_or_else(|| format!("no answer within {} seconds", PROBE_TIMEOUT.as_secs()))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(stderr
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("import failed")
+        .to_string())
+}
+
+fn check_interpreter(
+    ctx: &Context,
+    version: &str,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let interpreter = python_interpreter(ctx, version)
+        .ok_or_else(|| format!("No interpreter found for Python version {}", version))?;
+    let result = |name: &str, outcome: Result<String, String>| {
+        let passed = outcome.is_ok();
+        CheckResult {
+            name: name.to_string(),
+            passed,
+            detail: outcome.unwrap_or_else(|err| err),
+        }
+    };
+
+    let mut checks = vec![result(
+        "version",
+        probe_version(&interpreter).map(|(version, _)| version.to_string()),
+    )];
+    for module in CHECKED_MODULES {
+        checks.push(result(
+            module,
+            check_import(&interpreter, module).map(|_| "importable".to_string()),
+        ));
+    }
+    checks.push(result(
+        "pip",
+        pip_version(&interpreter).and_then(|pip| {
+            pip.map(|pip| format!("pip {}", pip))
+                .ok_or_else(|| "not installed".to_string())
+        }),
+    ));
+
+    if json {
+        #[derive(Serialize)]
+        struct CheckReport<'a> {
+            interpreter: &'a Path,
+            checks: &'a [CheckResult],
+        }
+        let report = CheckReport {
+            interpreter: &interpreter,
+            checks: &checks,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", interpreter.display());
+        let width = checks
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or(0);
+        for check in &checks {
+            println!(
+                "  {}  {:width$}  {}",
+                if check.passed { "pass" } else { "FAIL" },
+                check.name,
+                check.detail,
+                width = width
+            );
+        }
+    }
+
+    let failed = checks.iter().filter(|check| !check.passed).count();
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} check(s) failed", failed).into())
+    }
+}
+
+/// Finds `name` in the interpreter search path, returning where it was
+/// found and the binary it resolves to. Broken symlinks are skipped.
+fn resolve_on_path(ctx: &Context, name: &str) -> Option<(PathBuf, PathBuf)> {
+    python_search_dirs(ctx).into_iter().find_map(|dir| {
+        let candidate = dir.join(name);
+        let resolved = fs::canonicalize(&candidate).ok()?;
+        resolved.is_file().then_some((candidate, resolved))
+    })
+}
+
+fn default_python(ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+    let mut found = false;
+    for name in ["python3", "python"] {
+        let Some((candidate, resolved)) = resolve_on_path(ctx, name) else {
+            println!("{}: not found", name);
+            continue;
+        };
+        found = true;
+        let version = probe_version(&resolved)
+            .map(|(version, _)| Some(version))
+            .unwrap_or_else(|_| {
+                resolved
+                    .file_name()
+                    .and_then(|name| PythonVersion::parse(&name.to_string_lossy()))
+            });
+        let version = version.map_or_else(|| "unknown version".to_string(), |v| v.to_string());
+        if candidate == resolved {
+            println!("{}: {} ({})", name, resolved.display(), version);
+        } else {
+            println!(
+                "{}: {} -> {} ({})",
+                name,
+                candidate.display(),
+                resolved.display(),
+                version
+            );
+        }
+    }
+    if found {
+        Ok(())
+    } else {
+        Err("Neither python3 nor python was found on PATH".into())
+    }
+}
+
+/// One line about `project`, with its times relative to now if `relative`.
+fn describe_project(project: &Project, relative: bool) -> String {
+    let mut line = if project.favorite {
+        format!("* {}", project.name)
+    } else {
+        project.name.clone()
+    };
+    if let Some(description) = &project.description {
+        line.push_str(&format!(" - {}", description));
+    }
+    if !project.tags.is_empty() {
+        line.push_str(&format!(" [{}]", project.tags.join(", ")));
+    }
+    if let Some(path) = &project.path {
+        line.push_str(&format!(" at {}", path.display()));
+    }
+    if project.status.is_archived() {
+        line.push_str(" (archived)");
+    }
+    line.push_str(&format!(
+        " (created {}, last accessed {})",
+        format_time(project.created_at, relative),
+        format_time(project.last_accessed, relative)
+    ));
+    line
+}
+
+fn list_python_projects(ctx: &Context, version: &str, filters: &ListFilters, origin: bool) {
+    let log = load_project_log(ctx, version);
+    let projects = filters.apply(ctx, &log.projects);
+    let origins = if origin {
+        project_origins(ctx, &log)
+    } else {
+        BTreeMap::new()
+    };
+
+    if projects.is_empty() {
+        println!("No projects found for Python version {}", version);
+    } else {
+        println!("Projects worked on by Python version {}:", version);
+        for project in &projects {
+            match origins.get(&project.name) {
+                Some(origin) => println!(
+                    "{} [{}]",
+                    describe_project(project, filters.relative),
+                    origin.name()
+                ),
+                None => println!("{}", describe_project(project, filters.relative)),
+            }
+            if let Some(path) = &project.path {
+                if !path.exists() {
+                    println!("  warning: path {} no longer exists", path.display());
+                }
+            }
+        }
+        let stale_cutoff = current_timestamp().saturating_sub(90 * 24 * 60 * 60);
+        let stale = projects
+            .iter()
+            .filter(|p| p.last_accessed < stale_cutoff)
+            .count();
+        println!(
+            "{} projects ({} not accessed in 90+ days)",
+            projects.len(),
+            stale
+        );
+    }
+}
+
+fn list_all_projects(ctx: &Context, filters: &ListFilters) {
+    let mut total = 0;
+    let mut versions = 0;
+
+    for log in load_all_logs(ctx) {
+        let projects = filters.apply(ctx, &log.projects);
+        if projects.is_empty() {
+            continue;
+        }
+
+        println!("Python {} ({} projects):", log.version, projects.len());
+        for project in &projects {
+            println!("  {}", describe_project(project, filters.relative));
+        }
+        total += projects.len();
+        versions += 1;
+    }
+
+    if total == 0 {
+        println!("No projects found.");
+    } else {
+        println!("Total: {} projects across {} versions", total, versions);
+    }
+}
+
+fn canonical_project_path(path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    fs::canonicalize(path).map_err(|err| format!("Invalid path {}: {}", path.display(), err).into())
+}
+
+/// Picks the interpreter binary to record for a new project of `version`:
+/// `requested` if given, otherwise the only discovered one, or the user's
+/// choice when several match. Without a terminal, with `--yes`, or when
+/// `ask` is false because the terminal is taken, the preferred one is taken.
+fn choose_interpreter(
+    ctx: &Context,
+    version: &str,
+    requested: Option<&Path>,
+    ask: bool,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let candidates: Vec<PythonInstall> = get_python_versions(ctx)
+        .into_iter()
+        .filter(|install| install.log_key() == version)
+        .collect();
+    if let Some(requested) = requested {
+        let resolved = fs::canonicalize(requested)
+            .map_err(|err| format!("Interpreter {}: {}", requested.display(), err))?;
+        if !candidates.iter().any(|install| install.path == resolved) {
+            eprintln!(
+                "warning: {} is not a discovered interpreter for Python version {}",
+                requested.display(),
+                version
+            );
+        }
+        return Ok(Some(resolved));
+    }
+    if !ask || candidates.len() <= 1 || ctx.yes || !io::stdin().is_terminal() {
+        return Ok(preferred_interpreter(ctx, version));
+    }
+
+    println!("Interpreters for Python version {}:", version);
+    for (i, install) in candidates.iter().enumerate() {
+        let tag = install
+            .source_tag()
+            .map(|tag| format!(" [{}]", tag))
+            .unwrap_or_default();
+        println!("  {}) {}{}", i + 1, install.path.display(), tag);
+    }
+    loop {
+        let choice = prompt(&format!("Choose an interpreter [1-{}]: ", candidates.len()))?;
+        match choice.parse::<usize>() {
+            Ok(n) if (1..=candidates.len()).contains(&n) => {
+                return Ok(Some(candidates[n - 1].path.clone()))
+            }
+            _ => println!("Please enter a number between 1 and {}", candidates.len()),
+        }
+    }
+}
+
+/// The interpreter recorded for a new project of `version` when nobody is
+/// asked to choose: the first discovered one.
+fn preferred_interpreter(ctx: &Context, version: &str) -> Option<PathBuf> {
+    get_python_versions(ctx)
+        .into_iter()
+        .find(|install| install.log_key() == version)
+        .map(|install| install.path)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add_project(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    description: Option<&str>,
+    path: Option<&Path>,
+    repo_url: Option<&str>,
+    detect_repo: bool,
+    interpreter: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.map(canonical_project_path).transpose()?;
+    let repo_url = match (repo_url, &path) {
+        (Some(url), _) => Some(url.to_string()),
+        (None, Some(path)) if detect_repo => detect_repo_url(path),
+        _ => None,
+    };
+    let mut project = Project::new(project_name, current_timestamp());
+    project.description = description.map(str::to_string);
+    project.path = path;
+    project.repo_url = repo_url;
+    if insert_project(ctx, version, project, interpreter, true)? {
+        ctx.report(&format!(
+            "Project '{}' added to Python version {}",
+            project_name, version
+        ));
+    } else {
+        ctx.report(&format!(
+            "Project '{}' already exists for Python version {}, last accessed time updated",
+            project_name, version
+        ));
+    }
+    Ok(())
+}
+
+/// Adds `project` to the log of `version` with the interpreter picked by
+/// `choose_interpreter`, or, when a project of that name is logged already,
+/// only records an access to it. Returns whether the project was added.
+/// Shared by `add-project` and the table view, which passes `ask` false.
+fn insert_project(
+    ctx: &Context,
+    version: &str,
+    mut project: Project,
+    interpreter: Option<&Path>,
+    ask: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut log = try_load_project_log(ctx, version)?;
+    let added = match find_project(&log, &project.name) {
+        Some(index) => {
+            record_access(&mut log.projects[index], project.last_accessed);
+            false
+        }
+        None => {
+            project.interpreter_path = choose_interpreter(ctx, version, interpreter, ask)?;
+            log.projects.push(project);
+            true
+        }
+    };
+    save_changed_log(ctx, &log)?;
+    Ok(added)
+}
+
+/// Removes the project `name` from the log of `version`. Shared by
+/// `remove-project` and the table view.
+fn delete_project(
+    ctx: &Context,
+    version: &str,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = try_load_project_log(ctx, version)?;
+    let index = require_project(&log, name)?;
+    log.projects.remove(index);
+    save_changed_log(ctx, &log)
+}
+
+/// Saves `log` like `Context::save`, but returns a failure instead of
+/// exiting.
+fn save_changed_log(ctx: &Context, log: &ProjectLog) -> Result<(), Box<dyn std::error::Error>> {
+    ctx.try_save(log).map_err(|err| {
+        format!(
+            "could not write {}: {}",
+            log_path(ctx, &log.version).display(),
+            err
+        )
+        .into()
+    })
+}
+
+fn remove_project(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    require_project(&log, project_name)?;
+
+    if !ctx.confirm(&format!(
+        "Remove project '{}' from Python version {}?",
+        project_name, version
+    )) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    delete_project(ctx, version, project_name)?;
+    ctx.report(&format!(
+        "Project '{}' removed from Python version {}",
+        project_name, version
+    ));
+    Ok(())
+}
+
+fn remove_matching_projects(
+    ctx: &Context,
+    version: Option<&str>,
+    pattern: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let re = Regex::new(pattern)?;
+    let logs = match version {
+        Some(version) => vec![load_project_log(ctx, version)],
+        None => load_all_logs(ctx),
+    };
+
+    let mut matched = 0;
+    let mut pruned = Vec::new();
+    for mut log in logs {
+        let before = log.projects.len();
+        for project in log.projects.iter().filter(|p| re.is_match(&p.name)) {
+            println!("{} (Python {})", project.name, log.version);
+        }
+        log.projects.retain(|p| !re.is_match(&p.name));
+        if log.projects.len() < before {
+            matched += before - log.projects.len();
+            pruned.push(log);
+        }
+    }
+
+    if matched == 0 {
+        return Err(format!("No projects match '{}'", pattern).into());
+    }
+    if !ctx.confirm(&format!("Remove these {} project(s)?", matched)) {
+        println!("Aborted.");
+        return Ok(());
+    }
+    for log in &pruned {
+        ctx.save(log);
+    }
+    ctx.report(&format!("{} project(s) removed", matched));
+    Ok(())
+}
+
+fn rename_project(
+    ctx: &Context,
+    version: &str,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, old_name)?;
+
+    if find_project(&log, new_name).is_some() {
+        return Err(format!(
+            "Project '{}' already exists for Python version {}",
+            new_name, version
+        )
+        .into());
+    }
+
+    let project = &mut log.projects[index];
+    project.name = new_name.to_string();
+    project.last_accessed = current_timestamp();
+    ctx.save(&log);
+    ctx.report(&format!(
+        "Project '{}' renamed to '{}' for Python version {}",
+        old_name, new_name, version
+    ));
+    Ok(())
+}
+
+fn touch_projects(
+    ctx: &Context,
+    version: &str,
+    project_names: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let timestamp = current_timestamp();
+    let mut missing = Vec::new();
+    let mut touched = 0;
+
+    for name in project_names {
+        match find_project(&log, name) {
+            Some(index) => {
+                record_access(&mut log.projects[index], timestamp);
+                touched += 1;
+                ctx.report(&format!("Touched project '{}'", name));
+            }
+            None => {
+                println!("Project '{}' not found", name);
+                missing.push(name.as_str());
+            }
+        }
+    }
+
+    if touched > 0 {
+        ctx.save(&log);
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} project(s) not found for Python version {}: {}",
+            missing.len(),
+            version,
+            missing.join(", ")
+        )
+        .into())
+    }
+}
+
+fn move_project(
+    ctx: &Context,
+    from_version: &str,
+    to_version: &str,
+    project_name: &str,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if from_version == to_version {
+        return Err("Source and destination versions are the same".into());
+    }
+
+    let mut from_log = load_project_log(ctx, from_version);
+    let mut to_log = load_project_log(ctx, to_version);
+    let index = require_project(&from_log, project_name)?;
+
+    let mut project = from_log.projects[index].clone();
+    project.last_accessed = current_timestamp();
+
+    match find_project_by_name(&to_log, &project.name) {
+        Some(_) if !force => {
+            return Err(format!(
+                "Project '{}' already exists for Python version {} (use --force to replace it)",
+                project.name, to_version
+            )
+            .into());
+        }
+        Some(existing) => {
+            // On collision the entry with the newer creation time wins.
+            if project.created_at >= to_log.projects[existing].created_at {
+                to_log.projects[existing] = project;
+            } else {
+                to_log.projects[existing].last_accessed = project.last_accessed;
+            }
+        }
+        None => to_log.projects.push(project),
+    }
+
+    // Write the destination first so that a failure in between leaves the
+    // project in both logs rather than in neither.
+    ctx.save(&to_log);
+    from_log.projects.remove(index);
+    ctx.save(&from_log);
+    ctx.report(&format!(
+        "Project '{}' moved from Python version {} to {}",
+        project_name, from_version, to_version
+    ));
+    Ok(())
+}
+
+fn add_projects_from_file(
+    ctx: &Context,
+    version: &str,
+    file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = if file == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(file)
+            .map_err(|err| format!("Cannot read {}: {}", file.display(), err))?
+    };
+
+    let mut log = load_project_log(ctx, version);
+    let timestamp = current_timestamp();
+    let mut added = 0;
+    let mut skipped = 0;
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if find_project(&log, line).is_some() {
+            skipped += 1;
+            continue;
+        }
+        log.projects.push(Project::new(line, timestamp));
+        added += 1;
+    }
+
+    if added > 0 {
+        ctx.save(&log);
+    }
+    ctx.report(&format!(
+        "{} project(s) added to Python version {}, {} skipped as already existing",
+        added, version, skipped
+    ));
+    Ok(())
+}
+
+fn copy_project(
+    ctx: &Context,
+    from_version: &str,
+    to_version: &str,
+    project_name: &str,
+    overwrite: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if from_version == to_version {
+        return Err("Source and destination versions are the same".into());
+    }
+
+    let from_log = load_project_log(ctx, from_version);
+    let mut to_log = load_project_log(ctx, to_version);
+    let index = require_project(&from_log, project_name)?;
+
+    let mut project = from_log.projects[index].clone();
+    project.last_accessed = current_timestamp();
+
+    match find_project_by_name(&to_log, &project.name) {
+        Some(_) if !overwrite => {
+            return Err(format!(
+                "Project '{}' already exists for Python version {} (use --overwrite to replace it)",
+                project.name, to_version
+            )
+            .into());
+        }
+        Some(existing) => to_log.projects[existing] = project,
+        None => to_log.projects.push(project),
+    }
+
+    ctx.save(&to_log);
+    ctx.report(&format!(
+        "Project '{}' copied from Python version {} to {}",
+        project_name, from_version, to_version
+    ));
+    Ok(())
+}
+
+fn prompt(label: &str) -> io::Result<String> {
+    print!("{}", label);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input closed"));
+    }
+    Ok(line.trim().to_string())
+}
+
+fn add_project_interactive(ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+    if !io::stdin().is_terminal() {
+        return Err(
+            "--interactive needs a terminal; pass the version and project as arguments instead"
+                .into(),
+        );
+    }
+
+    let versions = version_names(&get_python_versions(ctx));
+    if versions.is_empty() {
+        return Err("No Python versions found.".into());
+    }
+    println!("Python versions found:");
+    for (i, version) in versions.iter().enumerate() {
+        println!("  {}) {}", i + 1, version);
+    }
+    let version = loop {
+        let choice = prompt(&format!("Choose a version [1-{}]: ", versions.len()))?;
+        match choice.parse::<usize>() {
+            Ok(n) if (1..=versions.len()).contains(&n) => break versions[n - 1].clone(),
+            _ => println!("Please enter a number between 1 and {}", versions.len()),
+        }
+    };
+
+    let name = loop {
+        let name = prompt("Project name: ")?;
+        if !name.is_empty() {
+            break name;
+        }
+        println!("The project name must not be empty");
+    };
+    let path = prompt("Project path (leave empty to skip): ")?;
+    let description = prompt("Description (leave empty to skip): ")?;
+
+    println!();
+    println!("Version:     {}", version);
+    println!("Project:     {}", name);
+    if !path.is_empty() {
+        println!("Path:        {}", path);
+    }
+    if !description.is_empty() {
+        println!("Description: {}", description);
+    }
+    if !ctx.confirm("Add this project?") {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    add_project(
+        ctx,
+        &version,
+        &name,
+        Some(description.as_str()).filter(|d| !d.is_empty()),
+        Some(Path::new(&path)).filter(|_| !path.is_empty()),
+        None,
+        false,
+        None,
+    )
+}
+
+fn set_description(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    text: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    log.projects[index].description = Some(text.to_string());
+    ctx.save(&log);
+    ctx.report(&format!(
+        "Description of project '{}' updated for Python version {}",
+        project_name, version
+    ));
+    Ok(())
+}
+
+fn set_path(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = canonical_project_path(path)?;
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    ctx.report(&format!(
+        "Path of project '{}' set to {}",
+        project_name,
+        path.display()
+    ));
+    log.projects[index].path = Some(path);
+    ctx.save(&log);
+    Ok(())
+}
+
+/// Reads the remote URL from the git config of the repository at `dir`,
+/// preferring `origin` over any other remote. Bare repositories keep their
+/// config at the top level instead of under `.git`.
+fn detect_repo_url(dir: &Path) -> Option<String> {
+    let config = [dir.join(".git").join("config"), dir.join("config")]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())?;
+
+    let mut section = String::new();
+    let mut remotes = Vec::new();
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "url" && section.starts_with("[remote ") {
+                remotes.push((section.clone(), value.trim().to_string()));
+            }
+        }
+    }
+    remotes
+        .iter()
+        .find(|(section, _)| section == "[remote \"origin\"]")
+        .or_else(|| remotes.first())
+        .map(|(_, url)| url.clone())
+}
+
+fn set_repo(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => {
+            let path = log.projects[index]
+                .path
+                .as_ref()
+                .ok_or_else(|| format!("Project '{}' has no path set", project_name))?;
+            detect_repo_url(path)
+                .ok_or_else(|| format!("No git remote found in {}", path.display()))?
+        }
+    };
+    ctx.report(&format!(
+        "Repository of project '{}' set to {}",
+        project_name, url
+    ));
+    log.projects[index].repo_url = Some(url);
+    ctx.save(&log);
+    Ok(())
+}
+
+fn open_project(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    exec: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let path = log.projects[index].path.clone().ok_or_else(|| {
+        format!(
+            "Project '{}' has no path recorded; set one with `pymanager set-path {} {} <path>`",
+            project_name, version, project_name
+        )
+    })?;
+    if !path.is_dir() {
+        return Err(format!("Project directory {} no longer exists", path.display()).into());
+    }
+    // The access is recorded after the shell exits; refuse now rather than
+    // then.
+    if ctx.read_only && !ctx.dry_run {
+        return Err(format!("data directory is read-only: {}", ctx.data_dir.display()).into());
+    }
+
+    let (shell, exec_flag) = if cfg!(windows) {
+        (
+            std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string()),
+            "/C",
+        )
+    } else {
+        (
+            std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()),
+            "-c",
+        )
+    };
+    let mut command = Command::new(&shell);
+    if let Some(exec) = exec {
+        command.arg(exec_flag).arg(exec);
+    }
+    let status = command.current_dir(&path).status()?;
+
+    // The lock is only taken now so that other commands can change the logs
+    // while the shell is open; reload in case they did.
+    ctx.lock_logs()?;
+    ctx.store.reload();
+    let mut log = load_project_log(ctx, version);
+    if let Some(index) = find_project(&log, project_name) {
+        record_access(&mut log.projects[index], current_timestamp());
+        ctx.save(&log);
+    }
+    ctx.unlock_logs();
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} exited with {}", shell, status).into())
+    }
+}
+
+fn search_projects(
+    ctx: &Context,
+    pattern: &str,
+    regex: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let matcher: Box<dyn Fn(&str) -> bool> = if regex {
+        let re = Regex::new(pattern)?;
+        Box::new(move |name| re.is_match(name))
+    } else {
+        let needle = pattern.to_lowercase();
+        Box::new(move |name| name.to_lowercase().contains(&needle))
+    };
+
+    let mut matches = 0;
+    for log in load_all_logs(ctx) {
+        for project in log.projects.iter().filter(|p| matcher(&p.name)) {
+            println!(
+                "{}: {} (created {}, last accessed {})",
+                log.version,
+                project.name,
+                format_timestamp(project.created_at),
+                format_timestamp(project.last_accessed)
+            );
+            matches += 1;
+        }
+    }
+
+    if matches == 0 {
+        ctx.exit_no_matches();
+    }
+    Ok(())
+}
+
+fn which_versions(
+    ctx: &Context,
+    name: &str,
+    fuzzy: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let needle = name.to_lowercase();
+    let mut matches = 0;
+    for log in load_all_logs(ctx) {
+        let found: Vec<&Project> = if fuzzy {
+            log.projects
+                .iter()
+                .filter(|p| p.name.to_lowercase().contains(&needle))
+                .collect()
+        } else {
+            find_project(&log, name)
+                .map(|index| &log.projects[index])
+                .into_iter()
+                .collect()
+        };
+        for project in found {
+            println!(
+                "{}: {} (created {}, last accessed {})",
+                log.version,
+                project.name,
+                format_timestamp(project.created_at),
+                format_timestamp(project.last_accessed)
+            );
+            matches += 1;
+        }
+    }
+
+    if matches == 0 {
+        ctx.exit_no_matches();
+    }
+    Ok(())
+}
+
+/// Lists the projects of `version`, flagging those whose recorded
+/// interpreter or `.venv` runs one of the version's interpreters and would
+/// break with it.
+fn version_impact(ctx: &Context, version: &str) {
+    let binaries: Vec<PathBuf> = get_python_versions(ctx)
+        .into_iter()
+        .filter(|install| install.log_key() == version)
+        .flat_map(|install| std::iter::once(install.path).chain(install.aliases))
+        .collect();
+    let log = load_project_log(ctx, version);
+    if log.projects.is_empty() {
+        println!("No projects logged for Python version {}.", version);
+        return;
+    }
+
+    let mut tied = 0;
+    println!("Projects logged under {}:", version);
+    for project in &log.projects {
+        let venv = project
+            .path
+            .as_ref()
+            .map(|path| venv_python(&path.join(".venv")))
+            .and_then(|python| fs::canonicalize(python).ok())
+            .filter(|python| binaries.contains(python));
+        let interpreter = project
+            .interpreter_path
+            .as_ref()
+            .filter(|path| binaries.contains(path));
+        let reason = match (venv, interpreter) {
+            (Some(python), _) => format!("  (.venv uses {})", python.display()),
+            (None, Some(path)) => format!("  (added with {})", path.display()),
+            (None, None) => String::new(),
+        };
+        if !reason.is_empty() {
+            tied += 1;
+        }
+        println!("  {}{}", project.name, reason);
+    }
+    println!(
+        "{} project(s) logged, {} tied to an interpreter of {}",
+        log.projects.len(),
+        tied,
+        version
+    );
+}
+
+/// Prints the logs that have projects but no interpreter left, and the
+/// interpreters that no project is logged under.
+fn interpreter_coverage(ctx: &Context) {
+    let installed = version_names(&get_python_versions(ctx));
+    let logged: Vec<(String, usize)> = load_all_logs(ctx)
+        .into_iter()
+        .filter(|log| !log.projects.is_empty())
+        .map(|log| (log.version, log.projects.len()))
+        .collect();
+
+    let orphaned: Vec<&(String, usize)> = logged
+        .iter()
+        .filter(|(version, _)| !installed.contains(version))
+        .collect();
+    let unused: Vec<&String> = installed
+        .iter()
+        .filter(|version| !logged.iter().any(|(logged, _)| logged == *version))
+        .collect();
+
+    if orphaned.is_empty() {
+        println!("Every log with projects has an interpreter.");
+    } else {
+        println!("Logs with projects but no interpreter:");
+        for (version, count) in &orphaned {
+            println!("  {} ({} projects)", version, count);
+        }
+    }
+    if unused.is_empty() {
+        println!("Every interpreter has projects.");
+    } else {
+        println!("Interpreters with no projects:");
+        for version in unused {
+            println!("  {}", version);
+        }
+    }
+}
+
+fn prune_projects(ctx: &Context, version: Option<&str>, older_than_days: u64) {
+    let versions = match version {
+        Some(version) => vec![version.to_string()],
+        None => logged_versions(ctx),
+    };
+    let cutoff = current_timestamp().saturating_sub(older_than_days.saturating_mul(24 * 60 * 60));
+    let mut pruned = Vec::new();
+
+    for version in versions {
+        let mut log = load_project_log(ctx, &version);
+        let (stale, kept): (Vec<Project>, Vec<Project>) = log
+            .projects
+            .into_iter()
+            .partition(|p| p.last_accessed < cutoff);
+        log.projects = kept;
+
+        for project in &stale {
+            println!(
+                "{} from Python version {} (last accessed {})",
+                project.name,
+                version,
+                format_timestamp(project.last_accessed)
+            );
+        }
+        if !stale.is_empty() {
+            pruned.push((log, stale.len()));
+        }
+    }
+
+    let removed: usize = pruned.iter().map(|(_, count)| count).sum();
+    if removed > 0 && !ctx.confirm(&format!("Remove these {} project(s)?", removed)) {
+        println!("Aborted.");
+        return;
+    }
+    for (log, _) in &pruned {
+        ctx.save(log);
+    }
+    ctx.report(&format!("{} project(s) removed", removed));
+}
+
+fn set_archived(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    archived: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let project = &mut log.projects[index];
+
+    if project.status.is_archived() == archived {
+        println!(
+            "Project '{}' is already {}",
+            project_name,
+            if archived { "archived" } else { "active" }
+        );
+        return Ok(());
+    }
+
+    if archived {
+        project.status = ProjectStatus::Archived;
+        project.archived_at = Some(current_timestamp());
+    } else {
+        project.status = ProjectStatus::Active;
+        project.archived_at = None;
+    }
+    ctx.save(&log);
+    ctx.report(&format!(
+        "Project '{}' {} for Python version {}",
+        project_name,
+        if archived { "archived" } else { "unarchived" },
+        version
+    ));
+    Ok(())
+}
+
+fn add_note(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    text: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    log.projects[index].notes.push(Note {
+        text: text.to_string(),
+        created_at: current_timestamp(),
+    });
+    ctx.save(&log);
+    ctx.report(&format!("Note added to project '{}'", project_name));
+    Ok(())
+}
+
+fn list_notes(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let notes = &log.projects[index].notes;
+
+    if notes.is_empty() {
+        println!("No notes for project '{}'", project_name);
+    } else {
+        for note in notes {
+            println!("[{}] {}", format_timestamp(note.created_at), note.text);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ProjectRef {
+    version: String,
+    name: String,
+    timestamp: u64,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    total_projects: usize,
+    projects_per_version: BTreeMap<String, usize>,
+    oldest_project: Option<ProjectRef>,
+    most_recently_accessed: Option<ProjectRef>,
+    not_accessed_30_days: usize,
+    not_accessed_90_days: usize,
+    not_accessed_365_days: usize,
+}
+
+fn collect_stats(ctx: &Context) -> Stats {
+    let now = current_timestamp();
+    let day = 24 * 60 * 60;
+    let mut stats = Stats {
+        total_projects: 0,
+        projects_per_version: BTreeMap::new(),
+        oldest_project: None,
+        most_recently_accessed: None,
+        not_accessed_30_days: 0,
+        not_accessed_90_days: 0,
+        not_accessed_365_days: 0,
+    };
+
+    for log in load_all_logs(ctx) {
+        let version = &log.version;
+        stats.total_projects += log.projects.len();
+        stats
+            .projects_per_version
+            .insert(version.clone(), log.projects.len());
+
+        for project in &log.projects {
+            if stats
+                .oldest_project
+                .as_ref()
+                .is_none_or(|p| project.created_at < p.timestamp)
+            {
+                stats.oldest_project = Some(ProjectRef {
+                    version: version.clone(),
+                    name: project.name.clone(),
+                    timestamp: project.created_at,
+                });
+            }
+            if stats
+                .most_recently_accessed
+                .as_ref()
+                .is_none_or(|p| project.last_accessed > p.timestamp)
+            {
+                stats.most_recently_accessed = Some(ProjectRef {
+                    version: version.clone(),
+                    name: project.name.clone(),
+                    timestamp: project.last_accessed,
+                });
+            }
+
+            let idle = now.saturating_sub(project.last_accessed);
+            if idle >= 30 * day {
+                stats.not_accessed_30_days += 1;
+            }
+            if idle >= 90 * day {
+                stats.not_accessed_90_days += 1;
+            }
+            if idle >= 365 * day {
+                stats.not_accessed_365_days += 1;
+            }
+        }
+    }
+
+    stats
+}
+
+fn print_stats(ctx: &Context, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let stats = collect_stats(ctx);
+    let today = Local::now().date_naive();
+    let eol: Vec<(&String, &usize)> = stats
+        .projects_per_version
+        .iter()
+        .filter(|(version, count)| **count > 0 && is_eol_log(version, today))
+        .collect();
+    if !eol.is_empty() {
+        let projects: usize = eol.iter().map(|(_, count)| **count).sum();
+        let versions: Vec<&str> = eol.iter().map(|(version, _)| version.as_str()).collect();
+        eprintln!(
+            "warning: {} project(s) logged against end-of-life Python versions: {}",
+            projects,
+            versions.join(", ")
+        );
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("Total projects: {}", stats.total_projects);
+    println!("Projects per version:");
+    let mut per_version: Vec<_> = stats.projects_per_version.iter().collect();
+    per_version.sort_by_key(|(version, _)| version_sort_key(version));
+    for (version, count) in per_version {
+        println!("  {}: {}", version, count);
+    }
+    if let Some(p) = &stats.oldest_project {
+        println!(
+            "Oldest project: {} ({}, created {})",
+            p.name,
+            p.version,
+            format_timestamp(p.timestamp)
+        );
+    }
+    if let Some(p) = &stats.most_recently_accessed {
+        println!(
+            "Most recently accessed: {} ({}, accessed {})",
+            p.name,
+            p.version,
+            format_timestamp(p.timestamp)
+        );
+    }
+    println!("Not accessed in 30 days: {}", stats.not_accessed_30_days);
+    println!("Not accessed in 90 days: {}", stats.not_accessed_90_days);
+    println!("Not accessed in 365 days: {}", stats.not_accessed_365_days);
+    Ok(())
+}
+
+/// Normalizes a project name the way PyPI compares distribution names:
+/// case-insensitive, with runs of `-`, `_` and `.` treated as equivalent.
+fn normalize_project_name(name: &str) -> String {
+    let re = Regex::new(r"[-_.]+").unwrap();
+    re.replace_all(&name.to_lowercase(), "-").into_owned()
+}
+
+fn find_duplicates(ctx: &Context, exact: bool) {
+    let mut names: BTreeMap<String, Vec<(String, Project)>> = BTreeMap::new();
+    for log in load_all_logs(ctx) {
+        for project in log.projects {
+            let key = if exact {
+                project.name.clone()
+            } else {
+                normalize_project_name(&project.name)
+            };
+            names
+                .entry(key)
+                .or_default()
+                .push((log.version.clone(), project));
+        }
+    }
+
+    let mut found = false;
+    for (name, entries) in names.iter().filter(|(_, e)| e.len() > 1) {
+        found = true;
+        println!("{}:", name);
+        for (version, project) in entries {
+            println!(
+                "  {} as '{}' (last accessed {})",
+                version,
+                project.name,
+                format_timestamp(project.last_accessed)
+            );
+        }
+    }
+
+    if !found {
+        println!("No duplicate projects found.");
+    }
+}
+
+const PROJECT_MARKERS: [&str; 3] = ["pyproject.toml", "setup.py", ".python-version"];
+const SCAN_SKIP_DIRS: [&str; 6] = [
+    ".git",
+    ".venv",
+    "venv",
+    "node_modules",
+    "__pycache__",
+    ".tox",
+];
+
+/// Collects every directory under `dir` that looks like a Python project.
+/// Project directories are not descended into further.
+fn find_python_projects(dir: &Path, found: &mut Vec<PathBuf>) {
+    if PROJECT_MARKERS.iter().any(|m| dir.join(m).is_file()) {
+        found.push(dir.to_path_buf());
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut children: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|e| !SCAN_SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+        .map(|e| e.path())
+        .collect();
+    children.sort();
+    for child in children {
+        find_python_projects(&child, found);
+    }
+}
+
+/// Extracts an `X.Y` version from strings like `3.11.4`, `>=3.9` or `~=3.10.0`.
+fn minor_version(text: &str) -> Option<String> {
+    let re = Regex::new(r"(\d+)\.(\d+)").unwrap();
+    re.captures(text)
+        .map(|caps| format!("{}.{}", &caps[1], &caps[2]))
+}
+
+fn infer_python_version(project_dir: &Path) -> Option<String> {
+    if let Ok(contents) = fs::read_to_string(project_dir.join(".python-version")) {
+        if let Some(version) = contents.lines().next().and_then(minor_version) {
+            return Some(version);
+        }
+    }
+    let requires = [
+        (
+            "pyproject.toml",
+            r#"requires-python\s*=\s*["']([^"']+)["']"#,
+        ),
+        ("setup.py", r#"python_requires\s*=\s*["']([^"']+)["']"#),
+    ];
+    for (file, pattern) in requires {
+        if let Ok(contents) = fs::read_to_string(project_dir.join(file)) {
+            let re = Regex::new(pattern).unwrap();
+            if let Some(version) = re
+                .captures(&contents)
+                .and_then(|caps| minor_version(&caps[1]))
+            {
+                return Some(version);
+            }
+        }
+    }
+    None
+}
+
+fn scan_projects(
+    ctx: &Context,
+    dir: &Path,
+    default_version: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = canonical_project_path(dir)?;
+    let mut found = Vec::new();
+    find_python_projects(&dir, &mut found);
+
+    let mut plan: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let mut unassigned = Vec::new();
+    for project_dir in found {
+        match infer_python_version(&project_dir).or_else(|| default_version.map(str::to_string)) {
+            Some(version) => plan.entry(version).or_default().push(project_dir),
+            None => unassigned.push(project_dir),
+        }
+    }
+
+    let timestamp = current_timestamp();
+    let mut added = 0;
+    for (version, dirs) in &plan {
+        let mut log = load_project_log(ctx, version);
+        let mut changed = false;
+        for project_dir in dirs {
+            let name = project_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| project_dir.display().to_string());
+            if find_project(&log, &name).is_some() {
+                println!(
+                    "Skipping '{}' (already logged for Python {})",
+                    name, version
+                );
+                continue;
+            }
+            ctx.report(&format!(
+                "Adding '{}' to Python version {} ({})",
+                name,
+                version,
+                project_dir.display()
+            ));
+            let mut project = Project::new(&name, timestamp);
+            project.path = Some(project_dir.clone());
+            log.projects.push(project);
+            changed = true;
+            added += 1;
+        }
+        if changed {
+            ctx.save(&log);
+        }
+    }
+
+    ctx.report(&format!("{} project(s) added", added));
+    if !unassigned.is_empty() {
+        println!("Could not infer a Python version for (use --default-version):");
+        for project_dir in unassigned {
+            println!("  {}", project_dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// Version of the `export` document layout. Bump it whenever the layout
+/// changes incompatibly so that `import` can reject documents it does not
+/// understand.
+const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// The document written by `export` and read by `import`:
+///
+/// ```json
+/// {
+///   "schema_version": 1,
+///   "exported_at": 1718000000,
+///   "versions": {
+///     "3.11": [ { "name": "...", "created_at": 0, "last_accessed": 0, ... } ]
+///   }
+/// }
+/// ```
+///
+/// `versions` maps each logged Python version to its projects, serialized
+/// exactly as they are in the per-version log files.
+#[derive(Serialize, Deserialize)]
+struct ExportDocument {
+    schema_version: u32,
+    exported_at: u64,
+    versions: BTreeMap<String, Vec<Project>>,
+}
+
+fn export_logs(ctx: &Context, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let document = ExportDocument {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        exported_at: current_timestamp(),
+        versions: load_all_logs(ctx)
+            .into_iter()
+            .map(|log| (log.version, log.projects))
+            .collect(),
+    };
+    let data = serde_json::to_string_pretty(&document)?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, data)
+                .map_err(|err| format!("Cannot write {}: {}", path.display(), err))?;
+            eprintln!(
+                "Exported {} versions to {}",
+                document.versions.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", data),
+    }
+    Ok(())
+}
+
+/// Folds `other` into `local`, keeping the earliest creation time and the
+/// latest access time. Returns whether `local` changed.
+fn merge_project(local: &mut Project, other: &Project) -> bool {
+    let before = (
+        local.created_at,
+        local.last_accessed,
+        local.access_log.clone(),
+    );
+    local.created_at = local.created_at.min(other.created_at);
+    local.last_accessed = local.last_accessed.max(other.last_accessed);
+    local.access_log.extend_from_slice(&other.access_log);
+    local.access_log.sort_unstable();
+    local.access_log.dedup();
+    trim_access_log(&mut local.access_log);
+    before
+        != (
+            local.created_at,
+            local.last_accessed,
+            local.access_log.clone(),
+        )
+}
+
+fn import_logs(ctx: &Context, file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let data = fs::read_to_string(file)
+        .map_err(|err| format!("Cannot read {}: {}", file.display(), err))?;
+    let document: ExportDocument = serde_json::from_str(&data)
+        .map_err(|err| format!("{} is not a pymanager export: {}", file.display(), err))?;
+    if document.schema_version != EXPORT_SCHEMA_VERSION {
+        return Err(format!(
+            "Unsupported export schema version {} (expected {})",
+            document.schema_version, EXPORT_SCHEMA_VERSION
+        )
+        .into());
+    }
+
+    for (version, projects) in document.versions {
+        let mut log = load_project_log(ctx, &version);
+        let (mut added, mut updated, mut unchanged) = (0, 0, 0);
+
+        for project in projects {
+            match find_project_by_name(&log, &project.name) {
+                Some(index) => {
+                    if merge_project(&mut log.projects[index], &project) {
+                        updated += 1;
+                    } else {
+                        unchanged += 1;
+                    }
+                }
+                None => {
+                    log.projects.push(project);
+                    added += 1;
+                }
+            }
+        }
+
+        ctx.report(&format!(
+            "Imported Python {}: {} added, {} updated, {} unchanged",
+            version, added, updated, unchanged
+        ));
+        if added + updated > 0 {
+            ctx.save(&log);
+        }
+    }
+    Ok(())
+}
+
+fn merge_version(ctx: &Context, from: &str, into: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if from == into {
+        return Err("Source and destination versions are the same".into());
+    }
+    if !log_exists(ctx, from) {
+        return Err(format!("No log found for Python version {}", from).into());
+    }
+
+    let from_log = load_project_log(ctx, from);
+    let mut into_log = load_project_log(ctx, into);
+    let (mut added, mut merged) = (0, 0);
+    for project in from_log.projects {
+        match find_project_by_name(&into_log, &project.name) {
+            Some(index) => {
+                merge_project(&mut into_log.projects[index], &project);
+                merged += 1;
+            }
+            None => {
+                into_log.projects.push(project);
+                added += 1;
+            }
+        }
+    }
+
+    ctx.save(&into_log);
+    let merged_path = if ctx.dry_run {
+        set_aside_path(ctx, from, "merged")
+    } else {
+        ctx.set_aside_log(from, "merged")?
+    };
+    ctx.report(&format!(
+        "Merged Python {} into {}: {} added, {} merged; old log kept as {}",
+        from,
+        into,
+        added,
+        merged,
+        merged_path.display()
+    ));
+    Ok(())
+}
+
+fn rename_version(ctx: &Context, from: &str, to: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !log_exists(ctx, from) {
+        return Err(format!("No log found for Python version {}", from).into());
+    }
+    if log_exists(ctx, to) {
+        return Err(format!(
+            "A log for Python version {} already exists; use merge-version instead",
+            to
+        )
+        .into());
+    }
+
+    let mut log = load_project_log(ctx, from);
+    log.version = to.to_string();
+    ctx.save(&log);
+    if !ctx.dry_run {
+        ctx.remove_log(from)?;
+    }
+    ctx.report(&format!(
+        "Renamed Python version {} to {} ({} projects)",
+        from,
+        to,
+        log.projects.len()
+    ));
+    Ok(())
+}
+
+fn show_project(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let project = &log.projects[index];
+    let origin = project_origins(ctx, &log)[&project.name];
+
+    if json {
+        let mut value = serde_json::to_value(project)?;
+        value["version"] = serde_json::Value::from(version);
+        value["origin"] = serde_json::to_value(origin)?;
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    let mut fields = vec![
+        ("Name", project.name.clone()),
+        ("Version", version.to_string()),
+        ("Created", format_timestamp(project.created_at)),
+        ("Last accessed", format_timestamp(project.last_accessed)),
+    ];
+    if ctx.system_dir.is_some() {
+        let system_path = system_log_path(ctx, version);
+        fields.push((
+            "Origin",
+            match origin {
+                Origin::User => format!("user, {}", log_path(ctx, version).display()),
+                Origin::System => format!("system, {}", system_path.display()),
+                Origin::Shadowing => format!(
+                    "user, {}, shadowing the entry in {}",
+                    log_path(ctx, version).display(),
+                    system_path.display()
+                ),
+            },
+        ));
+    }
+    if let Some(description) = &project.description {
+        fields.push(("Description", description.clone()));
+    }
+    if !project.aliases.is_empty() {
+        fields.push(("Aliases", project.aliases.join(", ")));
+    }
+    if !project.tags.is_empty() {
+        fields.push(("Tags", project.tags.join(", ")));
+    }
+    if let Some(path) = &project.path {
+        fields.push(("Path", path.display().to_string()));
+    }
+    if let Some(repo_url) = &project.repo_url {
+        fields.push(("Repository", repo_url.clone()));
+    }
+    if let Some(interpreter) = &project.interpreter_path {
+        fields.push(("Interpreter", interpreter.display().to_string()));
+    }
+    if let Some(archived_at) = project.archived_at {
+        fields.push(("Archived", format_timestamp(archived_at)));
+    }
+    for (key, value) in &project.metadata {
+        fields.push(("Meta", format!("{} = {}", key, value)));
+    }
+    for note in &project.notes {
+        fields.push((
+            "Note",
+            format!("[{}] {}", format_timestamp(note.created_at), note.text),
+        ));
+    }
+
+    let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in fields {
+        println!(
+            "{:width$}  {}",
+            format!("{}:", key),
+            value,
+            width = width + 1
+        );
+    }
+    Ok(())
+}
+
+fn clear_log(
+    ctx: &Context,
+    version: &str,
+    delete_file: bool,
+    backup: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !log_exists(ctx, version) {
+        return Err(format!("No log found for Python version {}", version).into());
+    }
+    let path = log_path(ctx, version);
+    let mut log = load_project_log(ctx, version);
+    let count = log.projects.len();
+
+    if !ctx.confirm(&format!(
+        "Remove all {} project(s) logged for Python version {}?",
+        count, version
+    )) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    if backup {
+        // Only this version's log, whichever store it is a part of.
+        let backup_path = ctx.data_dir.join(format!("{}.json.bak", version));
+        if !ctx.dry_run {
+            write_atomic(&backup_path, canonical_json(&log)?.as_bytes())?;
+        }
+        ctx.report(&format!("Backup written to {}", backup_path.display()));
+    }
+
+    if delete_file {
+        if !ctx.dry_run {
+            ctx.remove_log(version)?;
+        }
+        ctx.report(&format!(
+            "Removed {} project(s) and deleted {}",
+            count,
+            path.display()
+        ));
+    } else {
+        log.projects.clear();
+        ctx.save(&log);
+        ctx.report(&format!(
+            "Removed {} project(s) from Python version {}",
+            count, version
+        ));
+    }
+    Ok(())
+}
+
+fn format_logs(ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+    let changed = ctx.store.format(ctx)?;
+    for path in &changed {
+        ctx.report(&format!("Reformatted {}", path.display()));
+    }
+    if changed.is_empty() {
+        println!("Every log is already formatted.");
+    }
+    Ok(())
+}
+
+/// Removes logs without projects. Logs that still hold projects are only
+/// removed with both `--orphans` and `--yes`, and only when no discovered
+/// interpreter has their version.
+fn compact_logs(ctx: &Context, orphans: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let installed = if orphans {
+        version_names(&get_python_versions(ctx))
+    } else {
+        Vec::new()
+    };
+    let mut removed = 0;
+    let mut orphaned = Vec::new();
+    for log in load_all_logs(ctx) {
+        if log.projects.is_empty() {
+            if !ctx.dry_run {
+                ctx.remove_log(&log.version)?;
+            }
+            ctx.report(&format!("Removed the empty log of Python {}", log.version));
+            removed += 1;
+        } else if orphans && !installed.contains(&log.version) {
+            orphaned.push(log);
+        }
+    }
+
+    for log in &orphaned {
+        if ctx.yes {
+            if !ctx.dry_run {
+                ctx.remove_log(&log.version)?;
+            }
+            ctx.report(&format!(
+                "Removed the orphaned log of Python {} ({} project(s))",
+                log.version,
+                log.projects.len()
+            ));
+            removed += 1;
+        } else {
+            println!(
+                "Orphaned log of Python {} ({} project(s)): no interpreter found",
+                log.version,
+                log.projects.len()
+            );
+        }
+    }
+    if !orphaned.is_empty() && !ctx.yes {
+        println!("Run `pymanager compact --orphans --yes` to remove the orphaned logs.");
+    } else if removed == 0 {
+        println!("Nothing to compact.");
+    }
+    Ok(())
+}
+
+/// Moves every log from the other layout into `to`. Each log is written in
+/// full before any is removed from the old layout, and the removals leave
+/// the usual backups behind.
+fn convert_layout(ctx: &Context, to: LogLayout) -> Result<(), Box<dyn std::error::Error>> {
+    let from = match to {
+        LogLayout::PerVersion => LogLayout::Single,
+        LogLayout::Single => LogLayout::PerVersion,
+    };
+    let versions = move_logs(
+        ctx,
+        (from.store(), &format!("the {} layout", from.name())),
+        (to.store(), &format!("the {} layout", to.name())),
+    )?;
+    ctx.report(&format!(
+        "Converted {} log(s) to the {} layout: {}",
+        versions.len(),
+        to.name(),
+        versions.join(", ")
+    ));
+    if ctx.layout != to {
+        print_config_hint("layout", to.name());
+    }
+    Ok(())
+}
+
+fn migrate_storage(ctx: &Context, to: StorageBackend) -> Result<(), Box<dyn std::error::Error>> {
+    let from = match to {
+        StorageBackend::Json => StorageBackend::Sqlite,
+        StorageBackend::Sqlite => StorageBackend::Json,
+    };
+    let versions = move_logs(
+        ctx,
+        (
+            from.store(ctx.layout),
+            &format!("the {} backend", from.name()),
+        ),
+        (to.store(ctx.layout), &format!("the {} backend", to.name())),
+    )?;
+    ctx.report(&format!(
+        "Migrated {} log(s) to the {} backend: {}",
+        versions.len(),
+        to.name(),
+        versions.join(", ")
+    ));
+    if ctx.backend != to {
+        print_config_hint("backend", to.name());
+    }
+    Ok(())
+}
+
+/// Moves every log from `source` to `target`, each given with how messages
+/// name it, returning the versions moved. Nothing is moved unless every
+/// log can be read and `target` has none yet.
+fn move_logs(
+    ctx: &Context,
+    (source, from): (Box<dyn LogStore>, &str),
+    (target, to): (Box<dyn LogStore>, &str),
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut existing = target.versions(ctx);
+    if !existing.is_empty() {
+        existing.sort_by_key(|version| version_sort_key(version));
+        return Err(format!(
+            "The data directory already has logs in {} ({}); merge or remove them first",
+            to,
+            existing.join(", ")
+        )
+        .into());
+    }
+    let mut versions = source.versions(ctx);
+    if versions.is_empty() {
+        return Err(format!("No logs in {} to convert", from).into());
+    }
+    versions.sort_by_key(|version| version_sort_key(version));
+
+    let mut logs = Vec::new();
+    for version in &versions {
+        let log = source.load(ctx, version).map_err(|err| {
+            format!(
+                "Cannot convert {}: {}",
+                source.location(ctx, version).display(),
+                err
+            )
+        })?;
+        logs.push(log);
+    }
+    if !ctx.dry_run {
+        for log in &logs {
+            target.save(ctx, log)?;
+        }
+        for version in &versions {
+            source.remove(ctx, version)?;
+        }
+    }
+    Ok(versions)
+}
+
+/// Tells the user which config setting makes a conversion take effect.
+fn print_config_hint(key: &str, value: &str) {
+    let config = config_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "the config file".to_string());
+    println!("Set \"{}\": \"{}\" in {} to use it", key, value, config);
+}
+
+fn read_backup(ctx: &Context, version: &str, timestamp: &str) -> Result<ProjectLog, String> {
+    let path = backup_path(ctx, version, timestamp);
+    let data = read_maybe_compressed(&path)
+        .map_err(|err| format!("Cannot read {}: {}", path.display(), err))?;
+    serde_json::from_slice(&data).map_err(|err| format!("Cannot parse {}: {}", path.display(), err))
+}
+
+fn list_log_backups(ctx: &Context, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let backups = list_backups(ctx, version);
+    if backups.is_empty() {
+        return Err(format!("No backups found for Python version {}", version).into());
+    }
+    for timestamp in backups.iter().rev() {
+        let taken = NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        match read_backup(ctx, version, timestamp) {
+            Ok(log) => println!(
+                "{}  {}  {} project(s)",
+                timestamp,
+                taken,
+                log.projects.len()
+            ),
+            Err(err) => println!("{}  {}  unreadable: {}", timestamp, taken, err),
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the log of `version` with one of its backups. The log being
+/// replaced is itself backed up by the save, so a restore can be undone.
+fn restore_log(
+    ctx: &Context,
+    version: &str,
+    timestamp: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let backups = list_backups(ctx, version);
+    let timestamp = match timestamp {
+        Some(timestamp) => backups
+            .iter()
+            .find(|candidate| candidate.as_str() == timestamp)
+            .ok_or_else(|| {
+                format!(
+                    "No backup {} for Python version {}; see `pymanager restore {} --list`",
+                    timestamp, version, version
+                )
+            })?,
+        None => backups
+            .last()
+            .ok_or_else(|| format!("No backups found for Python version {}", version))?,
+    };
+    let mut restored = read_backup(ctx, version, timestamp)?;
+    restored.version = version.to_string();
+    let current = load_project_log(ctx, version);
+
+    if !ctx.confirm(&format!(
+        "Replace the log of Python version {} ({} project(s)) with the backup from {} ({} project(s))?",
+        version,
+        current.projects.len(),
+        timestamp,
+        restored.projects.len()
+    )) {
+        println!("Aborted.");
+        return Ok(());
+    }
+    ctx.save(&restored);
+    ctx.report(&format!(
+        "Restored Python {} from the backup taken at {}",
+        version, timestamp
+    ));
+    Ok(())
+}
+
+fn recent_projects(ctx: &Context, limit: usize, version: Option<&str>) {
+    let logs = match version {
+        Some(version) => vec![load_project_log(ctx, version)],
+        None => load_all_logs(ctx),
+    };
+    let mut entries: Vec<(String, Project)> = logs
+        .into_iter()
+        .flat_map(|log| {
+            let version = log.version;
+            log.projects.into_iter().map(move |p| (version.clone(), p))
+        })
+        .collect();
+    entries.sort_by(|(_, a), (_, b)| {
+        b.last_accessed
+            .cmp(&a.last_accessed)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    if entries.is_empty() {
+        println!("No projects found.");
+        return;
+    }
+    let now = current_timestamp();
+    for (version, project) in entries.into_iter().take(limit) {
+        println!(
+            "{:<8} {:<30} {}",
+            version,
+            project.name,
+            format_relative(project.last_accessed, now)
+        );
+    }
+}
+
+fn set_favorite(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    favorite: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    if log.projects[index].favorite == favorite {
+        println!(
+            "Project '{}' is already {}",
+            project_name,
+            if favorite { "pinned" } else { "unpinned" }
+        );
+        return Ok(());
+    }
+    log.projects[index].favorite = favorite;
+    ctx.save(&log);
+    ctx.report(&format!(
+        "Project '{}' {}",
+        project_name,
+        if favorite { "pinned" } else { "unpinned" }
+    ));
+    Ok(())
+}
+
+fn add_alias(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    alias: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let alias = alias.trim();
+    if alias.is_empty() {
+        return Err("Alias must not be empty".into());
+    }
+
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    if let Some(other) = find_project(&log, alias) {
+        let conflict = &log.projects[other].name;
+        return Err(if other == index {
+            format!("'{}' already refers to project '{}'", alias, conflict)
+        } else {
+            format!(
+                "Alias '{}' conflicts with project '{}' in Python version {}",
+                alias, conflict, version
+            )
+        }
+        .into());
+    }
+
+    let project = &mut log.projects[index];
+    project.aliases.push(alias.to_string());
+    ctx.report(&format!(
+        "Alias '{}' added to project '{}'",
+        alias, project.name
+    ));
+    ctx.save(&log);
+    Ok(())
+}
+
+fn remove_alias(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    alias: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let project = &mut log.projects[index];
+
+    let before = project.aliases.len();
+    project.aliases.retain(|a| a != alias);
+    if project.aliases.len() == before {
+        return Err(format!("Project '{}' has no alias '{}'", project.name, alias).into());
+    }
+    ctx.report(&format!(
+        "Alias '{}' removed from project '{}'",
+        alias, project.name
+    ));
+    ctx.save(&log);
+    Ok(())
+}
+
+fn validate_meta_key(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let re = Regex::new(r"^[A-Za-z_][A-Za-z0-9_.-]*$").unwrap();
+    if re.is_match(key) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid metadata key '{}': use letters, digits, '_', '.' or '-', starting with a letter or '_'",
+            key
+        )
+        .into())
+    }
+}
+
+fn set_meta(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_meta_key(key)?;
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    log.projects[index]
+        .metadata
+        .insert(key.to_string(), value.to_string());
+    ctx.save(&log);
+    ctx.report(&format!(
+        "Set {} = {} on project '{}'",
+        key, value, project_name
+    ));
+    Ok(())
+}
+
+fn get_meta(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    key: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    match log.projects[index].metadata.get(key) {
+        Some(value) => {
+            println!("{}", value);
+            Ok(())
+        }
+        None => Err(format!("Project '{}' has no metadata key '{}'", project_name, key).into()),
+    }
+}
+
+fn unset_meta(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    key: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    if log.projects[index].metadata.remove(key).is_none() {
+        return Err(format!("Project '{}' has no metadata key '{}'", project_name, key).into());
+    }
+    ctx.save(&log);
+    ctx.report(&format!("Removed {} from project '{}'", key, project_name));
+    Ok(())
+}
+
+fn show_history(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let project = &log.projects[index];
+
+    if project.access_log.is_empty() {
+        println!(
+            "No access history for project '{}' (last accessed {})",
+            project_name,
+            format_timestamp(project.last_accessed)
+        );
+    } else {
+        for timestamp in project.access_log.iter().rev() {
+            println!("{}", format_timestamp(*timestamp));
+        }
+    }
+    Ok(())
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+fn tag_project(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    tag: &str,
+    add: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tag = normalize_tag(tag);
+    if tag.is_empty() {
+        return Err("Tag must not be empty".into());
+    }
+
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+
+    let tags = &mut log.projects[index].tags;
+    if add {
+        if tags.contains(&tag) {
+            println!("Project '{}' is already tagged '{}'", project_name, tag);
+            return Ok(());
+        }
+        tags.push(tag.clone());
+        tags.sort();
+        tags.dedup();
+        ctx.save(&log);
+        ctx.report(&format!(
+            "Tag '{}' added to project '{}'",
+            tag, project_name
+        ));
+    } else {
+        let before = tags.len();
+        tags.retain(|t| *t != tag);
+        if tags.len() == before {
+            return Err(format!("Project '{}' is not tagged '{}'", project_name, tag).into());
+        }
+        ctx.save(&log);
+        ctx.report(&format!(
+            "Tag '{}' removed from project '{}'",
+            tag, project_name
+        ));
+    }
+    Ok(())
+}
+
+/// Sums the size of every file below `path`. Symlinks are counted as links
+/// and never followed, so a linked directory is not counted twice.
+fn directory_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| directory_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn project_sizes(ctx: &Context, version: Option<&str>, save: bool) {
+    let versions = match version {
+        Some(version) => vec![version.to_string()],
+        None => logged_versions(ctx),
+    };
+    let timestamp = current_timestamp();
+    let mut sizes = Vec::new();
+
+    for version in versions {
+        let mut log = load_project_log(ctx, &version);
+        for project in &mut log.projects {
+            let Some(path) = &project.path else {
+                continue;
+            };
+            if !path.exists() {
+                eprintln!(
+                    "warning: path {} of project '{}' no longer exists",
+                    path.display(),
+                    project.name
+                );
+                continue;
+            }
+            let size = directory_size(path);
+            project.size_bytes = Some(size);
+            project.size_computed_at = Some(timestamp);
+            sizes.push((size, project.name.clone(), version.clone()));
+        }
+        if save {
+            ctx.save(&log);
+        }
+    }
+
+    if sizes.is_empty() {
+        println!("No projects with a path found.");
+        return;
+    }
+    sizes.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    for (size, name, version) in &sizes {
+        println!("{:>10}  {} ({})", format_size(*size), name, version);
+    }
+    if save {
+        ctx.report(&format!("Sizes of {} project(s) saved", sizes.len()));
+    }
+}
+
+/// Asks the interpreter at `interpreter` for its site-packages directories.
+fn site_packages_dirs(interpreter: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let output = output_with_timeout(
+        Command::new(interpreter).args([
+            "-c",
+            "import site,json;print(json.dumps(site.getsitepackages()))",
+        ]),
+        PROBE_TIMEOUT,
+    )
+    .map_err(|err| format!("could not run {}: {}", interpreter.display(), err))?
+    .ok_or_else(|| {
+        format!(
+            "{} did not answer within {} seconds",
+            interpreter.display(),
+            PROBE_TIMEOUT.as_secs()
+        )
+    })?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            interpreter.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    let dirs: Vec<PathBuf> = serde_json::from_slice(&output.stdout)
+        .map_err(|err| format!("unexpected output from {}: {}", interpreter.display(), err))?;
+    Ok(dirs)
+}
+
+fn site_packages(
+    ctx: &Context,
+    version: &str,
+    size: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let interpreter = python_interpreter(ctx, version)
+        .ok_or_else(|| format!("No interpreter found for Python version {}", version))?;
+    if !interpreter.is_file() {
+        return Err(format!("Interpreter {} does not exist", interpreter.display()).into());
+    }
+    let dirs = site_packages_dirs(&interpreter)?;
+    if dirs.is_empty() {
+        println!(
+            "{} reports no site-packages directories.",
+            interpreter.display()
+        );
+        return Ok(());
+    }
+    if !size {
+        for dir in &dirs {
+            println!("{}", dir.display());
+        }
+        return Ok(());
+    }
+    let mut total = 0;
+    for dir in &dirs {
+        if dir.exists() {
+            let bytes = directory_size(dir);
+            total += bytes;
+            println!("{:>10}  {}", format_size(bytes), dir.display());
+        } else {
+            println!("{:>10}  {} (missing)", "-", dir.display());
+        }
+    }
+    println!("{:>10}  total", format_size(total));
+    Ok(())
+}
+
+/// Creates the virtualenv and `.python-version` file in a freshly created
+/// project directory.
+fn init_project_dir(
+    interpreter: &Path,
+    version: &str,
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new(interpreter)
+        .args(["-m", "venv", ".venv"])
+        .current_dir(dir)
+        .status()?;
+    if !status.success() {
+        return Err(format!("{} -m venv exited with {}", interpreter.display(), status).into());
+    }
+    fs::write(dir.join(".python-version"), format!("{}\n", version))?;
+    Ok(())
+}
+
+fn init_project(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+    dir: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(project_name));
+    if dir.exists() {
+        return Err(format!("{} already exists", dir.display()).into());
+    }
+    let interpreter = python_interpreter(ctx, version)
+        .ok_or_else(|| format!("No interpreter found for Python version {}", version))?;
+    let mut log = load_project_log(ctx, version);
+    if find_project(&log, project_name).is_some() {
+        return Err(format!(
+            "Project '{}' already exists for Python version {}",
+            project_name, version
+        )
+        .into());
+    }
+
+    if !ctx.dry_run {
+        fs::create_dir_all(&dir)?;
+        // Remove the directory again if any step fails, saving the log
+        // included, so nothing half-made is left behind.
+        let created = init_project_dir(&interpreter, version, &dir)
+            .and_then(|_| canonical_project_path(&dir))
+            .and_then(|path| {
+                let mut project = Project::new(project_name, current_timestamp());
+                project.path = Some(path);
+                project.interpreter_path = fs::canonicalize(&interpreter).ok();
+                log.projects.push(project);
+                save_changed_log(ctx, &log)
+            });
+        if let Err(err) = created {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(err);
+        }
+    }
+    ctx.report(&format!(
+        "Project '{}' created in {} with {}",
+        project_name,
+        dir.display(),
+        interpreter.display()
+    ));
+    Ok(())
+}
+
+/// How long `pip freeze` may run before it is killed.
+const FREEZE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The interpreter inside a virtualenv.
+fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+/// The interpreter of a project: its `.venv` when there is one, otherwise the
+/// system interpreter of `version`.
+fn project_interpreter(ctx: &Context, project: &Project, version: &str) -> Option<PathBuf> {
+    project
+        .path
+        .as_ref()
+        .map(|path| venv_python(&path.join(".venv")))
+        .filter(|python| python.is_file())
+        .or_else(|| python_interpreter(ctx, version))
+}
+
+fn pip_freeze(interpreter: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let output = output_with_timeout(
+        Command::new(interpreter).args(["-m", "pip", "freeze"]),
+        FREEZE_TIMEOUT,
+    )?
+    .ok_or_else(|| {
+        format!(
+            "pip freeze did not finish within {} seconds",
+            FREEZE_TIMEOUT.as_secs()
+        )
+    })?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else if stderr.contains("No module named pip") {
+        Err(format!("pip is not available for {}", interpreter.display()).into())
+    } else {
+        Err(format!("pip freeze failed: {}", stderr.trim()).into())
+    }
+}
+
+fn snapshot_requirements(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = load_project_log(ctx, version);
+    let index = require_project(&log, project_name)?;
+    let interpreter = project_interpreter(ctx, &log.projects[index], version)
+        .ok_or_else(|| format!("No interpreter found for Python version {}", version))?;
+    let requirements = pip_freeze(&interpreter)?;
+
+    let project = &mut log.projects[index];
+    ctx.report(&format!(
+        "Stored {} requirement(s) for project '{}'",
+        requirements.lines().count(),
+        project.name
+    ));
+    project.requirements_snapshot = Some(requirements);
+    project.requirements_snapshot_at = Some(current_timestamp());
+    ctx.save(&log);
+    Ok(())
+}
+
+/// Maps package names to their `pip freeze` line.
+fn parse_requirements(requirements: &str) -> BTreeMap<String, String> {
+    requirements
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let name = line
+                .split(['=', ' ', '@'])
+                .next()
+                .unwrap_or(line)
+                .to_lowercase();
+            (name, line.to_string())
+        })
+        .collect()
+}
+
+fn diff_requirements(
+    ctx: &Context,
+    version: &str,
+    project_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log = load_project_log(ctx, version);
+    let project = &log.projects[require_project(&log, project_name)?];
+    let (Some(snapshot), Some(taken_at)) = (
+        &project.requirements_snapshot,
+        project.requirements_snapshot_at,
+    ) else {
+        return Err(format!("Project '{}' has no requirements snapshot", project.name).into());
+    };
+    let interpreter = project_interpreter(ctx, project, version)
+        .ok_or_else(|| format!("No interpreter found for Python version {}", version))?;
+
+    let old = parse_requirements(snapshot);
+    let new = parse_requirements(&pip_freeze(&interpreter)?);
+    println!(
+        "Changes since the snapshot of {}:",
+        format_timestamp(taken_at)
+    );
+    let mut changed = false;
+    for (name, line) in &new {
+        match old.get(name) {
+            None => println!("+ {}", line),
+            Some(old_line) if old_line != line => println!("~ {} -> {}", old_line, line),
+            Some(_) => continue,
+        }
+        changed = true;
+    }
+    for (name, line) in &old {
+        if !new.contains_key(name) {
+            println!("- {}", line);
+            changed = true;
+        }
+    }
+    if !changed {
+        println!("No changes.");
+    }
+    Ok(())
+}
+
+/// Folds two entries of the same project into one: the earliest creation,
+/// the latest access, and the union of tags, aliases, notes, metadata and
+/// access history. Everything else is taken from whichever entry was
+/// accessed last.
+fn merge_project_entries(first: &Project, second: &Project) -> Project {
+    let (older, newer) = if second.last_accessed >= first.last_accessed {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    let mut merged = newer.clone();
+    merged.created_at = first.created_at.min(second.created_at);
+    for tag in &older.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    for alias in &older.aliases {
+        if !merged.aliases.contains(alias) {
+            merged.aliases.push(alias.clone());
+        }
+    }
+    for note in &older.notes {
+        if !merged.notes.contains(note) {
+            merged.notes.push(note.clone());
+        }
+    }
+    merged.notes.sort_by_key(|note| note.created_at);
+    for (key, value) in &older.metadata {
+        merged
+            .metadata
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+    for (key, value) in &older.extra {
+        merged
+            .extra
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+    merged.access_log.extend(&older.access_log);
+    merged.access_log.sort_unstable();
+    merged.access_log.dedup();
+    trim_access_log(&mut merged.access_log);
+    merged.favorite |= older.favorite;
+    merged.description = merged.description.or_else(|| older.description.clone());
+    merged.path = merged.path.or_else(|| older.path.clone());
+    merged.repo_url = merged.repo_url.or_else(|| older.repo_url.clone());
+    merged.interpreter_path = merged
+        .interpreter_path
+        .or_else(|| older.interpreter_path.clone());
+    merged
+}
+
+/// Merges the entries of `projects` that share a name into the first of
+/// them, returning each merged name with how many entries it had.
+fn dedupe_projects(projects: &mut Vec<Project>) -> Vec<(String, usize)> {
+    let mut merged: Vec<(String, usize)> = Vec::new();
+    let mut kept: Vec<Project> = Vec::new();
+    for project in projects.drain(..) {
+        match kept.iter_mut().find(|other| other.name == project.name) {
+            Some(other) => {
+                *other = merge_project_entries(other, &project);
+                match merged.iter_mut().find(|(name, _)| *name == project.name) {
+                    Some((_, count)) => *count += 1,
+                    None => merged.push((project.name.clone(), 2)),
+                }
+            }
+            None => kept.push(project),
+        }
+    }
+    *projects = kept;
+    merged
+}
+
+fn dedupe_log(ctx: &Context, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !log_exists(ctx, version) {
+        return Err(format!("No log found for Python version {}", version).into());
+    }
+    let mut log = load_project_log(ctx, version);
+    let merged = dedupe_projects(&mut log.projects);
+    if merged.is_empty() {
+        println!("No duplicate projects in Python version {}", version);
+        return Ok(());
+    }
+    for (name, count) in &merged {
+        let project = log
+            .projects
+            .iter()
+            .find(|project| project.name == *name)
+            .expect("merged projects are kept");
+        ctx.report(&format!(
+            "Merged {} entries of '{}' in Python version {} (created {}, last accessed {})",
+            count,
+            name,
+            version,
+            format_timestamp(project.created_at),
+            format_timestamp(project.last_accessed)
+        ));
+    }
+    ctx.save(&log);
+    Ok(())
+}
+
+fn validate_logs(ctx: &Context, fix: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let installed = version_names(&get_python_versions(ctx));
+    let mut logs = load_all_logs(ctx);
+
+    let mut missing_paths = Vec::new();
+    let mut missing_interpreters = Vec::new();
+    let mut unknown_versions = Vec::new();
+    let mut duplicate_names = Vec::new();
+    for log in &logs {
+        if !installed.contains(&log.version) {
+            unknown_versions.push(log.version.clone());
+        }
+        let mut seen = BTreeSet::new();
+        let mut duplicates = BTreeSet::new();
+        for project in &log.projects {
+            if let Some(path) = &project.path {
+                if !path.exists() {
+                    missing_paths.push((log.version.clone(), project.name.clone(), path.clone()));
+                }
+            }
+            if let Some(interpreter) = &project.interpreter_path {
+                if !interpreter.exists() {
+                    missing_interpreters.push((
+                        log.version.clone(),
+                        project.name.clone(),
+                        interpreter.clone(),
+                    ));
+                }
+            }
+            if !seen.insert(project.name.as_str()) {
+                duplicates.insert(project.name.clone());
+            }
+        }
+        for name in duplicates {
+            duplicate_names.push((log.version.clone(), name));
+        }
+    }
+
+    if !missing_paths.is_empty() {
+        println!("Projects whose path no longer exists:");
+        for (version, name, path) in &missing_paths {
+            println!("  {} ({}): {}", name, version, path.display());
+        }
+    }
+    if !missing_interpreters.is_empty() {
+        println!("Projects whose interpreter no longer exists:");
+        for (version, name, interpreter) in &missing_interpreters {
+            println!("  {} ({}): {}", name, version, interpreter.display());
+        }
+    }
+    if !unknown_versions.is_empty() {
+        println!("Logs without a matching Python interpreter:");
+        for version in &unknown_versions {
+            println!("  {}", version);
+        }
+    }
+    if !duplicate_names.is_empty() {
+        println!("Duplicate project names within a log:");
+        for (version, name) in &duplicate_names {
+            println!("  {} ({})", name, version);
+        }
+        println!("  merge them with `pymanager dedupe <version>` or `pymanager validate --fix`");
+    }
+    // Not a problem with the logs themselves, so only a warning.
+    let today = Local::now().date_naive();
+    for log in &logs {
+        if !log.projects.is_empty() && is_eol_log(&log.version, today) {
+            eprintln!(
+                "warning: {} project(s) logged against end-of-life Python {}",
+                log.projects.len(),
+                log.version
+            );
+        }
+    }
+
+    let problems = missing_paths.len()
+        + missing_interpreters.len()
+        + unknown_versions.len()
+        + duplicate_names.len();
+    if problems == 0 {
+        println!("No problems found.");
+        return Ok(());
+    }
+
+    if fix
+        && !missing_paths.is_empty()
+        && ctx.confirm(&format!(
+            "Clear {} dangling path reference(s)?",
+            missing_paths.len()
+        ))
+    {
+        for log in &mut logs {
+            let mut changed = false;
+            for project in &mut log.projects {
+                if project.path.as_ref().is_some_and(|path| !path.exists()) {
+                    project.path = None;
+                    changed = true;
+                }
+            }
+            if changed {
+                ctx.save(log);
+            }
+        }
+        ctx.report(&format!(
+            "{} dangling path reference(s) cleared",
+            missing_paths.len()
+        ));
+    }
+    if fix
+        && !duplicate_names.is_empty()
+        && ctx.confirm(&format!(
+            "Merge {} duplicate project name(s)?",
+            duplicate_names.len()
+        ))
+    {
+        for log in &mut logs {
+            if !dedupe_projects(&mut log.projects).is_empty() {
+                ctx.save(log);
+            }
+        }
+        ctx.report(&format!(
+            "{} duplicate project name(s) merged",
+            duplicate_names.len()
+        ));
+    }
+
+    Err(format!("{} problem(s) found", problems).into())
+}
+
+/// Text shown in the TUI popup for a project.
+fn project_details_text(version: &str, project: &Project) -> String {
+    let mut lines = vec![
+        format!("Name: {}", project.name),
+        format!("Version: {}", version),
+        format!("Created: {}", format_timestamp(project.created_at)),
+        format!("Last accessed: {}", format_timestamp(project.last_accessed)),
+    ];
+    if let Some(description) = &project.description {
+        lines.push(format!("Description: {}", description));
+    }
+    if let Some(path) = &project.path {
+        lines.push(format!("Path: {}", path.display()));
+    }
+    if !project.tags.is_empty() {
+        lines.push(format!("Tags: {}", project.tags.join(", ")));
+    }
+    if !project.aliases.is_empty() {
+        lines.push(format!("Aliases: {}", project.aliases.join(", ")));
+    }
+    if let Some(interpreter) = &project.interpreter_path {
+        lines.push(format!("Interpreter: {}", interpreter.display()));
+    }
+    if let Some(archived_at) = project.archived_at {
+        lines.push(format!("Archived: {}", format_timestamp(archived_at)));
+    }
+    if let Some(repo_url) = &project.repo_url {
+        lines.push(format!("Repository: {}", repo_url));
+    }
+    if !project.metadata.is_empty() {
+        lines.push("Metadata:".to_string());
+        for (key, value) in &project.metadata {
+            lines.push(format!("  {} = {}", key, value));
+        }
+    }
+    if project.notes.is_empty() {
+        lines.push("No notes.".to_string());
+    } else {
+        lines.push("Notes:".to_string());
+        for note in &project.notes {
+            lines.push(format!(
+                "  [{}] {}",
+                format_timestamp(note.created_at),
+                note.text
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Returns a rectangle of the given percentage size centered in `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// How often the table checks the data directories for changes made by
+/// other processes.
+const TUI_RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a notice stays in the table's status line.
+const TUI_NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+/// The projects of every log of `versions`, passing the logs that cannot be
+/// read to `skip`.
+fn table_entries(
+    ctx: &Context,
+    versions: &[String],
+    mut skip: impl FnMut(&LogError),
+) -> Vec<(String, Project)> {
+    let mut entries = Vec::new();
+    for version in versions {
+        match try_load_project_log(ctx, version) {
+            Ok(log) => {
+                for project in log.projects {
+                    entries.push((version.clone(), project));
+                }
+            }
+            Err(err) => skip(&err),
+        }
+    }
+    entries
+}
+
+/// The size and modification time of every log file in the data
+/// directories, which changes whenever another process writes a log.
+fn log_files_fingerprint(ctx: &Context) -> Vec<(PathBuf, u64, Option<SystemTime>)> {
+    let mut files = Vec::new();
+    for dir in std::iter::once(&ctx.data_dir).chain(&ctx.system_dir) {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if !is_log_file(&path) {
+                continue;
+            }
+            if let Ok(meta) = entry.metadata() {
+                files.push((path, meta.len(), meta.modified().ok()));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Where a navigation key moves the selection in a table of `len` rows of
+/// which `page` fit on screen, kept within the table.
+fn move_selection(selected: usize, key: KeyCode, len: usize, page: usize) -> usize {
+    let last = len.saturating_sub(1);
+    let moved = match key {
+        KeyCode::Up => selected.saturating_sub(1),
+        KeyCode::Down => selected + 1,
+        KeyCode::PageUp => selected.saturating_sub(page),
+        KeyCode::PageDown => selected + page,
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        _ => selected,
+    };
+    moved.min(last)
+}
+
+/// What the table view shows on top of the table, and so where keys go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TableView {
+    Table,
+    /// Everything about the selected project
+    Details,
+    Environments,
+    /// Asks whether to delete the selected project
+    ConfirmDelete,
+    /// A failed change, until dismissed
+    Error,
+    /// The form to add a project
+    Add,
+}
+
+/// The form the table view opens to add a project.
+struct AddForm {
+    version: String,
+    name: String,
+    /// Whether the name field has the focus rather than the version field.
+    on_name: bool,
+    /// Position of the cursor in the focused field, in characters.
+    cursor: usize,
+    /// Why the last submit was refused.
+    error: Option<String>,
+}
+
+impl AddForm {
+    fn new(version: &str) -> Self {
+        AddForm {
+            version: version.to_string(),
+            name: String::new(),
+            on_name: true,
+            cursor: 0,
+            error: None,
+        }
+    }
+
+    fn field(&mut self) -> &mut String {
+        if self.on_name {
+            &mut self.name
+        } else {
+            &mut self.version
+        }
+    }
+
+    /// Moves the focus to the other field, with the cursor at its end.
+    fn switch_field(&mut self) {
+        self.on_name = !self.on_name;
+        self.cursor = self.field().chars().count();
+    }
+
+    /// Replaces the version with the next (or previous) discovered one.
+    fn cycle_version(&mut self, versions: &[String], backwards: bool) {
+        if versions.is_empty() {
+            return;
+        }
+        let next = match versions.iter().position(|version| *version == self.version) {
+            Some(0) if backwards => versions.len() - 1,
+            Some(index) if backwards => index - 1,
+            Some(index) => (index + 1) % versions.len(),
+            None => 0,
+        };
+        self.version = versions[next].clone();
+        self.cursor = self.version.chars().count();
+    }
+
+    /// Applies an editing key to the focused field.
+    fn edit(&mut self, key: KeyCode) {
+        let cursor = self.cursor;
+        let field = self.field();
+        let len = field.chars().count();
+        let byte = |index: usize| {
+            field
+                .char_indices()
+                .nth(index)
+                .map_or(field.len(), |(byte, _)| byte)
+        };
+        match key {
+            KeyCode::Char(c) => {
+                field.insert(byte(cursor), c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if cursor > 0 => {
+                field.remove(byte(cursor - 1));
+                self.cursor -= 1;
+            }
+            KeyCode::Delete if cursor < len => {
+                field.remove(byte(cursor));
+            }
+            KeyCode::Left => self.cursor = cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            _ => {}
+        }
+    }
+}
+
+/// Adds a project from the table view through `insert_project`, except
+/// that an existing project is refused rather than touched. The lock is
+/// only held for the change, and the log is read again under it.
+fn add_from_table(ctx: &Context, version: &str, name: &str) -> Result<(), String> {
+    if version.is_empty() {
+        return Err("The version must not be empty".to_string());
+    }
+    if name.is_empty() {
+        return Err("The project name must not be empty".to_string());
+    }
+    ctx.lock_logs()?;
+    ctx.store.reload();
+    let result = try_load_project_log(ctx, version)
+        .map_err(|err| err.to_string())
+        .and_then(|log| match find_project(&log, name) {
+            Some(_) => Err(format!(
+                "Project '{}' already exists for Python version {}",
+                name, version
+            )),
+            None => {
+                let project = Project::new(name, current_timestamp());
+                insert_project(ctx, version, project, None, false)
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            }
+        });
+    ctx.unlock_logs();
+    result
+}
+
+/// Removes a project from the table view through `delete_project`. The
+/// lock is only held for the change, and the log is read again under it.
+fn delete_from_table(ctx: &Context, version: &str, name: &str) -> Result<(), String> {
+    ctx.lock_logs()?;
+    ctx.store.reload();
+    let result = delete_project(ctx, version, name).map_err(|err| err.to_string());
+    ctx.unlock_logs();
+    result
+}
+
+/// The row to select once row `index` of `rows` is deleted: the one below
+/// it, or the one above when it was the last.
+fn delete_neighbor(rows: &[&(String, Project)], index: usize) -> Option<(String, String)> {
+    rows.get(index + 1)
+        .or_else(|| rows.get(index.checked_sub(1)?))
+        .map(|(version, project)| (version.clone(), project.name.clone()))
+}
+
+/// The header of a table column, marked with the direction when the table
+/// is sorted by it.
+fn column_title(title: &str, column: TableSort, sort: Option<(TableSort, bool)>) -> String {
+    match sort {
+        Some((sorted, false)) if sorted == column => format!("{} ▲", title),
+        Some((sorted, true)) if sorted == column => format!("{} ▼", title),
+        _ => title.to_string(),
+    }
+}
+
+/// Shows the projects of every installed version. `sort` is the column to
+/// sort by and whether to sort it in descending order.
+fn show_table(
+    ctx: &Context,
+    mut sort: Option<(TableSort, bool)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let versions = version_names(&get_python_versions(ctx));
+
+    // Logs are loaded before the terminal switches to the table, so that
+    // warnings about unreadable ones stay readable.
+    let mut fingerprint = log_files_fingerprint(ctx);
+    let mut entries = table_entries(ctx, &versions, |err| ctx.skip_unreadable(err));
+    let mut last_check = Instant::now();
+    let mut notice: Option<(String, Instant)> = None;
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    for version in &versions {
+        println!("Python version listed: {}", version);
+    }
+
+    let environments = format_environment_rows(&environment_rows(ctx, false)).join("\n");
+
+    let mut include_archived = false;
+    let mut view = TableView::Table;
+    let mut error_message = String::new();
+    let mut form = AddForm::new("");
+    // A project to select once the rows are rebuilt, such as one just added.
+    let mut select_next: Option<(String, String)> = None;
+    let mut state = TableState::default();
+    // Rows that fit in the table, known once it has been drawn.
+    let mut page = 1;
+    // Typed after `/`; only projects whose name or version contains it are
+    // shown, ignoring case.
+    let mut filter = String::new();
+    let mut editing_filter = false;
+    let mut relative_times = false;
+    let header = ctx.theme.header_style();
+
+    loop {
+        if notice
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= TUI_NOTICE_DURATION)
+        {
+            notice = None;
+        }
+        if last_check.elapsed() >= TUI_RELOAD_INTERVAL {
+            last_check = Instant::now();
+            let current = log_files_fingerprint(ctx);
+            if current != fingerprint {
+                fingerprint = current;
+                ctx.store.reload();
+                // Warnings would garble the table, so they go to the
+                // status line instead.
+                let mut unreadable = 0;
+                entries = table_entries(ctx, &versions, |_| unreadable += 1);
+                let message = if unreadable > 0 {
+                    format!("reloaded; {} log(s) could not be read", unreadable)
+                } else {
+                    "reloaded".to_string()
+                };
+                notice = Some((message, Instant::now()));
+            }
+        }
+        let query = filter.to_lowercase();
+        let mut visible: Vec<&(String, Project)> = entries
+            .iter()
+            .filter(|(_, project)| include_archived || !project.status.is_archived())
+            .filter(|(version, project)| {
+                project.name.to_lowercase().contains(&query)
+                    || version.to_lowercase().contains(&query)
+            })
+            .collect();
+        if let Some((column, descending)) = sort {
+            visible.sort_by(|a, b| {
+                let order = column.compare(a, b);
+                if descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+        }
+        match state.selected() {
+            _ if visible.is_empty() => state.select(None),
+            Some(selected) if selected >= visible.len() => state.select(Some(visible.len() - 1)),
+            None => state.select(Some(0)),
+            _ => {}
+        }
+        if let Some((version, name)) = select_next.take() {
+            if let Some(index) = visible
+                .iter()
+                .position(|(v, project)| *v == version && project.name == name)
+            {
+                state.select(Some(index));
+            }
+        }
+        let selected = state.selected().map(|i| visible[i]);
+        let visible_count = visible.len();
+        // The project shown may have gone with a reload.
+        if matches!(view, TableView::Details | TableView::ConfirmDelete) && selected.is_none() {
+            view = TableView::Table;
+        }
+
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|(version, project)| {
+                Row::new(vec![
+                    Cell::from(if project.favorite { "*" } else { "" }),
+                    Cell::from(version.clone()),
+                    Cell::from(project.name.clone()),
+                    Cell::from(project.description.clone().unwrap_or_default()),
+                    Cell::from(project.tags.join(", ")),
+                    Cell::from(format_time(project.created_at, relative_times)),
+                    Cell::from(format_time(project.last_accessed, relative_times)),
+                    Cell::from(project.size_bytes.map(format_size).unwrap_or_default()),
+                ])
+            })
+            .collect();
+        let mut title = if include_archived {
+            "Python Projects (including archived)".to_string()
+        } else {
+            "Python Projects".to_string()
+        };
+        if let Some(index) = state.selected() {
+            title.push_str(&format!(" {}/{}", index + 1, visible.len()));
+        }
+
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(1)])
+                .split(f.size());
+            let size = chunks[0];
+            // The borders and the header row take three lines.
+            page = usize::from(size.height.saturating_sub(3)).max(1);
+            let matches = format!("{} match(es)", visible_count);
+            let status = if editing_filter {
+                f.set_cursor(chunks[1].x + 1 + filter.chars().count() as u16, chunks[1].y);
+                format!("/{}  {}", filter, matches)
+            } else if let Some((message, _)) = &notice {
+                message.clone()
+            } else if !filter.is_empty() {
+                format!("filter: {}  {}  / edit  Esc clear", filter, matches)
+            } else {
+                "q quit  a add  d delete  A archived  e environments  1-4 sort  / filter  t times  Enter details".to_string()
+            };
+            f.render_widget(Paragraph::new(status), chunks[1]);
+            let block = Block::default().borders(Borders::ALL).title(title);
+            let table = Table::new(rows)
+                .block(block)
+                .header(Row::new(vec![
+                        Cell::from("*").style(header),
+                        Cell::from(column_title("Version", TableSort::Version, sort)).style(header),
+                        Cell::from(column_title("Project", TableSort::Name, sort)).style(header),
+                        Cell::from("Description").style(header),
+                        Cell::from("Tags").style(header),
+                        Cell::from(column_title("Created At", TableSort::Created, sort))
+                            .style(header),
+                        Cell::from(column_title("Last Accessed", TableSort::Accessed, sort))
+                            .style(header),
+                        Cell::from("Size").style(header),
+                ]))
+                .widths(&[
+                    Constraint::Length(1),
+                    Constraint::Percentage(10),
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(20),
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(10),
+                ])
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            f.render_stateful_widget(table, size, &mut state);
+
+            if let (TableView::Details, Some((version, project))) = (view, selected) {
+                let area = centered_rect(70, 70, size);
+                let popup = Paragraph::new(project_details_text(version, project))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(format!("{} ({})", project.name, version)),
+                    )
+                    .wrap(Wrap { trim: false });
+                f.render_widget(Clear, area);
+                f.render_widget(popup, area);
+            }
+            if let (TableView::ConfirmDelete, Some((version, project))) = (view, selected) {
+                let area = centered_rect(50, 20, size);
+                let popup = Paragraph::new(format!(
+                    "Delete {} from {}? (y/n)",
+                    project.name, version
+                ))
+                .block(Block::default().borders(Borders::ALL).title("Delete"))
+                .wrap(Wrap { trim: false });
+                f.render_widget(Clear, area);
+                f.render_widget(popup, area);
+            }
+            if view == TableView::Add {
+                let area = centered_rect(60, 30, size);
+                let mut lines = vec![
+                    format!("Version: {}", form.version),
+                    format!("Project: {}", form.name),
+                    String::new(),
+                    "Tab next version  Up/Down switch field  Enter save  Esc cancel"
+                        .to_string(),
+                ];
+                if let Some(error) = &form.error {
+                    lines.push(error.clone());
+                }
+                let popup = Paragraph::new(lines.join("\n"))
+                    .block(Block::default().borders(Borders::ALL).title("Add project"))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(Clear, area);
+                f.render_widget(popup, area);
+                // Both labels are nine characters wide.
+                f.set_cursor(
+                    area.x + 10 + form.cursor as u16,
+                    area.y + 1 + u16::from(form.on_name),
+                );
+            }
+            if view == TableView::Error {
+                let area = centered_rect(60, 25, size);
+                let popup = Paragraph::new(error_message.as_str())
+                    .block(Block::default().borders(Borders::ALL).title("Error"))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(Clear, area);
+                f.render_widget(popup, area);
+            }
+            if view == TableView::Environments {
+                let area = centered_rect(80, 60, size);
+                let text = if environments.is_empty() {
+                    "No environments found."
+                } else {
+                    environments.as_str()
+                };
+                let popup = Paragraph::new(text)
+                    .block(Block::default().borders(Borders::ALL).title("Environments"))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(Clear, area);
+                f.render_widget(popup, area);
+            }
+        })?;
+
+        if !event::poll(TUI_RELOAD_INTERVAL.saturating_sub(last_check.elapsed()))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            match (view, key.code) {
+                (TableView::Table, _) => {}
+                (TableView::ConfirmDelete, KeyCode::Char('y')) => {
+                    view = TableView::Table;
+                    if let Some((version, project)) = selected {
+                        let (version, name) = (version.clone(), project.name.clone());
+                        let neighbor = state
+                            .selected()
+                            .and_then(|index| delete_neighbor(&visible, index));
+                        match delete_from_table(ctx, &version, &name) {
+                            Ok(()) => {
+                                select_next = neighbor;
+                                // Our own write is not news to the reload check.
+                                fingerprint = log_files_fingerprint(ctx);
+                                entries = table_entries(ctx, &versions, |_| {});
+                                let message = if ctx.dry_run {
+                                    format!("would delete {} from {}", name, version)
+                                } else {
+                                    format!("deleted {} from {}", name, version)
+                                };
+                                notice = Some((message, Instant::now()));
+                            }
+                            Err(err) => {
+                                error_message = err;
+                                view = TableView::Error;
+                            }
+                        }
+                    }
+                    continue;
+                }
+                (TableView::Add, KeyCode::Esc) => {
+                    view = TableView::Table;
+                    continue;
+                }
+                (TableView::Add, KeyCode::Enter) if !form.on_name => {
+                    form.switch_field();
+                    continue;
+                }
+                (TableView::Add, KeyCode::Enter) => {
+                    let (version, name) = (form.version.trim(), form.name.trim());
+                    match add_from_table(ctx, version, name) {
+                        Ok(()) => {
+                            fingerprint = log_files_fingerprint(ctx);
+                            entries = table_entries(ctx, &versions, |_| {});
+                            let message = if ctx.dry_run {
+                                format!("would add {} to {}", name, version)
+                            } else {
+                                format!("added {} to {}", name, version)
+                            };
+                            notice = Some((message, Instant::now()));
+                            select_next = Some((version.to_string(), name.to_string()));
+                            view = TableView::Table;
+                        }
+                        Err(err) => form.error = Some(err),
+                    }
+                    continue;
+                }
+                (TableView::Add, KeyCode::Up | KeyCode::Down) => {
+                    form.switch_field();
+                    continue;
+                }
+                (TableView::Add, KeyCode::Tab | KeyCode::BackTab) if !form.on_name => {
+                    form.cycle_version(&versions, key.code == KeyCode::BackTab);
+                    continue;
+                }
+                (TableView::Add, code) => {
+                    form.edit(code);
+                    continue;
+                }
+                (TableView::Environments, KeyCode::Char('e') | KeyCode::Esc)
+                | (TableView::Details, KeyCode::Enter | KeyCode::Esc)
+                | (TableView::ConfirmDelete, KeyCode::Char('n') | KeyCode::Esc)
+                | (TableView::Error, _) => {
+                    view = TableView::Table;
+                    continue;
+                }
+                _ => continue,
+            }
+            if editing_filter {
+                match key.code {
+                    KeyCode::Char(c) => filter.push(c),
+                    KeyCode::Backspace => {
+                        filter.pop();
+                    }
+                    KeyCode::Esc => {
+                        filter.clear();
+                        editing_filter = false;
+                    }
+                    KeyCode::Enter => editing_filter = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') => break,
+                KeyCode::Char('A') => include_archived = !include_archived,
+                KeyCode::Char('a') if ctx.read_only => {
+                    error_message = format!(
+                        "The data directory {} is read-only; projects cannot be added.",
+                        ctx.data_dir.display()
+                    );
+                    view = TableView::Error;
+                }
+                KeyCode::Char('a') => {
+                    let version = selected
+                        .map(|(version, _)| version.as_str())
+                        .or(versions.first().map(String::as_str))
+                        .unwrap_or_default();
+                    form = AddForm::new(version);
+                    view = TableView::Add;
+                }
+                KeyCode::Char('e') => view = TableView::Environments,
+                KeyCode::Char('d') if selected.is_some() => {
+                    if ctx.read_only {
+                        error_message = format!(
+                            "The data directory {} is read-only; projects cannot be deleted.",
+                            ctx.data_dir.display()
+                        );
+                        view = TableView::Error;
+                    } else {
+                        view = TableView::ConfirmDelete;
+                    }
+                }
+                KeyCode::Char('/') => editing_filter = true,
+                KeyCode::Char('t') => relative_times = !relative_times,
+                KeyCode::Esc => filter.clear(),
+                KeyCode::Char(key) => {
+                    // Choosing the sorted column again flips its direction.
+                    if let Some(column) = TableSort::from_key(key) {
+                        sort = Some(match sort {
+                            Some((sorted, descending)) if sorted == column => (column, !descending),
+                            _ => (column, false),
+                        });
+                    }
+                }
+                KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::PageUp
+                | KeyCode::PageDown
+                | KeyCode::Home
+                | KeyCode::End => state.select(
+                    state
+                        .selected()
+                        .map(|i| move_selection(i, key.code, visible.len(), page)),
+                ),
+                KeyCode::Enter if selected.is_some() => view = TableView::Details,
+                _ => {}
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let config = match load_config() {
+        Ok(config) => config,
+        // `config set` has to work to repair the file, and `doctor` reports it.
+        Err(err)
+            if matches!(
+                cli.command,
+                Commands::Config { .. } | Commands::Doctor { .. }
+            ) =>
+        {
+            eprintln!("warning: {}", err);
+            Config::default()
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let exclude_patterns = match config
+        .exclude_patterns
+        .iter()
+        .map(|pattern| parse_exclude_pattern(pattern))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(patterns) => patterns
+            .into_iter()
+            .chain(cli.exclude.iter().cloned())
+            .collect(),
+        Err(err) => {
+            eprintln!("Error: config: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let data_dir = cli
+        .data_dir
+        .clone()
+        .or_else(|| {
+            std::env::var_os("PYMANAGER_DATA_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        })
+        .or_else(|| config.data_dir.clone())
+        .unwrap_or_else(|| resolve_data_dir(cli.dry_run || cli.read_only));
+    let read_only = cli.read_only || (data_dir.exists() && !is_writable_dir(&data_dir));
+    let system_dir = match std::env::var_os("PYMANAGER_SYSTEM_DATA_DIR") {
+        Some(dir) if dir.is_empty() => None,
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => config.system_data_dir.clone(),
+    }
+    .filter(|dir| dir.is_dir() && *dir != data_dir);
+    let backend = cli.backend.unwrap_or(config.backend);
+    let store: Box<dyn LogStore> = match system_dir {
+        Some(_) => Box::new(LayeredStore {
+            user: backend.store(config.layout),
+        }),
+        None => backend.store(config.layout),
+    };
+    let ctx = Context {
+        command: command_name(&matches),
+        yes: cli.yes,
+        dry_run: cli.dry_run,
+        verbose: cli.verbose,
+        strict: cli.strict,
+        default_sort: config.default_sort,
+        theme: config.theme,
+        python_dirs: config
+            .extra_python_dirs
+            .into_iter()
+            .chain(cli.python_dirs.iter().cloned())
+            .collect(),
+        env_python_dirs: std::env::var_os("PYMANAGER_PYTHON_DIRS")
+            .map(|dirs| std::env::split_paths(&dirs).collect()),
+        refresh: cli.refresh,
+        discovery_ttl: config.discovery_cache_ttl,
+        backup_count: config.backup_count,
+        venv_roots: std::env::var_os("HOME")
+            .map(|home| Path::new(&home).join(".virtualenvs"))
+            .into_iter()
+            .chain(config.venv_roots)
+            .collect(),
+        exclude_patterns,
+        min_version: cli.min_version,
+        data_dir,
+        system_dir,
+        read_only,
+        backend,
+        layout: config.layout,
+        store: Box::new(CachedStore::new(store)),
+        log_lock: RefCell::new(None),
+    };
+    if cli.command.changes_logs() {
+        if let Err(err) = ctx.lock_logs() {
+            ctx.exit_with_error(&err);
+        }
+    }
+
+    let result = match &cli.command {
+        Commands::ListPythonVersions { options } => list_python_versions(&ctx, options),
+        Commands::DefaultPython => default_python(&ctx),
+        Commands::CheckPip { version } => check_pip(&ctx, version),
+        Commands::Check { version, json } => check_interpreter(&ctx, version, *json),
+        Commands::ListEnvs => {
+            list_envs(&ctx);
+            Ok(())
+        }
+        Commands::ListPythonProjects {
+            version,
+            filters,
+            origin,
+        } => {
+            list_python_projects(&ctx, version, filters, *origin);
+            Ok(())
+        }
+        Commands::ListAllProjects { filters } => {
+            list_all_projects(&ctx, filters);
+            Ok(())
+        }
+        Commands::AddProject {
+            version,
+            project,
+            description,
+            path,
+            repo_url,
+            detect_repo,
+            snapshot_requirements: snapshot,
+            interpreter,
+            from_file,
+            interactive,
+        } => match (version, from_file, project) {
+            _ if *interactive => add_project_interactive(&ctx),
+            (Some(version), Some(file), _) => add_projects_from_file(&ctx, version, file),
+            (Some(version), None, Some(project)) => add_project(
+                &ctx,
+                version,
+                project,
+                description.as_deref(),
+                path.as_deref(),
+                repo_url.as_deref(),
+                *detect_repo,
+                interpreter.as_deref(),
+            )
+            // A dry run did not add the project, so there is nothing to snapshot.
+            .and_then(|_| {
+                if *snapshot && !ctx.dry_run {
+                    snapshot_requirements(&ctx, version, project)
+                } else {
+                    Ok(())
+                }
+            }),
+            _ => unreachable!("clap requires a version and a project or --from-file"),
+        },
+        Commands::RemoveProject {
+            version,
+            project,
+            pattern,
+            all_versions: _,
+        } => match (pattern, version, project) {
+            (Some(pattern), version, _) => {
+                remove_matching_projects(&ctx, version.as_deref(), pattern)
+            }
+            (None, Some(version), Some(project)) => remove_project(&ctx, version, project),
+            _ => unreachable!("clap requires a version and a project or --match"),
+        },
+        Commands::RenameProject {
+            version,
+            old_name,
+            new_name,
+        } => rename_project(&ctx, version, old_name, new_name),
+        Commands::Touch { version, projects } => touch_projects(&ctx, version, projects),
+        Commands::MoveProject {
+            from_version,
+            to_version,
+            project,
+            force,
+        } => move_project(&ctx, from_version, to_version, project, *force),
+        Commands::CopyProject {
+            from_version,
+            to_version,
+            project,
+            overwrite,
+        } => copy_project(&ctx, from_version, to_version, project, *overwrite),
+        Commands::SetDescription {
+            version,
+            project,
+            text,
+        } => set_description(&ctx, version, project, text),
+        Commands::SetPath {
+            version,
+            project,
+            path,
+        } => set_path(&ctx, version, project, path),
+        Commands::SetRepo {
+            version,
+            project,
+            url,
+            detect: _,
+        } => set_repo(&ctx, version, project, url.as_deref()),
+        Commands::Open {
+            version,
+            project,
+            exec,
+        } => open_project(&ctx, version, project, exec.as_deref()),
+        Commands::Search { pattern, regex } => search_projects(&ctx, pattern, *regex),
+        Commands::Which { project, fuzzy } => which_versions(&ctx, project, *fuzzy),
+        Commands::Impact { version, all: _ } => {
+            match version {
+                Some(version) => version_impact(&ctx, version),
+                None => interpreter_coverage(&ctx),
+            }
+            Ok(())
+        }
+        Commands::Prune {
+            version,
+            older_than,
+            all_versions: _,
+        } => {
+            prune_projects(&ctx, version.as_deref(), *older_than);
+            Ok(())
+        }
+        Commands::Archive { version, project } => set_archived(&ctx, version, project, true),
+        Commands::Unarchive { version, project } => set_archived(&ctx, version, project, false),
+        Commands::Note {
+            version,
+            project,
+            text,
+        } => add_note(&ctx, version, project, text),
+        Commands::Notes { version, project } => list_notes(&ctx, version, project),
+        Commands::Stats { json } => print_stats(&ctx, *json),
+        Commands::Duplicates {
+            exact,
+            normalized: _,
+        } => {
+            find_duplicates(&ctx, *exact);
+            Ok(())
+        }
+        Commands::Scan {
+            dir,
+            default_version,
+        } => scan_projects(&ctx, dir, default_version.as_deref()),
+        Commands::Export { output } => export_logs(&ctx, output.as_deref()),
+        Commands::Import { file } => import_logs(&ctx, file),
+        Commands::Show {
+            version,
+            project,
+            json,
+        } => show_project(&ctx, version, project, *json),
+        Commands::Clear {
+            version,
+            delete_file,
+            backup,
+        } => clear_log(&ctx, version, *delete_file, *backup),
+        Commands::ConvertLayout { to } => convert_layout(&ctx, *to),
+        Commands::MigrateStorage { to } => migrate_storage(&ctx, *to),
+        Commands::FormatLogs => format_logs(&ctx),
+        Commands::Compact { orphans } => compact_logs(&ctx, *orphans),
+        Commands::Restore {
+            version,
+            list,
+            backup,
+        } => {
+            if *list {
+                list_log_backups(&ctx, version)
+            } else {
+                restore_log(&ctx, version, backup.as_deref())
+            }
+        }
+        Commands::Journal { limit, project } => show_journal(&ctx, *limit, project.as_deref()),
+        Commands::Recent { limit, version } => {
+            recent_projects(&ctx, *limit, version.as_deref());
+            Ok(())
+        }
+        Commands::Pin { version, project } => set_favorite(&ctx, version, project, true),
+        Commands::Unpin { version, project } => set_favorite(&ctx, version, project, false),
+        Commands::MergeVersion { from, into } => merge_version(&ctx, from, into),
+        Commands::RenameVersion { from, to } => rename_version(&ctx, from, to),
+        Commands::Alias { action } => match action {
+            AliasAction::Add {
+                version,
+                project,
+                alias,
+            } => add_alias(&ctx, version, project, alias),
+            AliasAction::Remove {
+                version,
+                project,
+                alias,
+            } => remove_alias(&ctx, version, project, alias),
+        },
+        Commands::Meta { action } => match action {
+            MetaAction::Set {
+                version,
+                project,
+                key,
+                value,
+            } => set_meta(&ctx, version, project, key, value),
+            MetaAction::Get {
+                version,
+                project,
+                key,
+            } => get_meta(&ctx, version, project, key),
+            MetaAction::Unset {
+                version,
+                project,
+                key,
+            } => unset_meta(&ctx, version, project, key),
+        },
+        Commands::History { version, project } => show_history(&ctx, version, project),
+        Commands::Tag { action } => match action {
+            TagAction::Add {
+                version,
+                project,
+                tag,
+            } => tag_project(&ctx, version, project, tag, true),
+            TagAction::Remove {
+                version,
+                project,
+                tag,
+            } => tag_project(&ctx, version, project, tag, false),
+        },
+        Commands::Size { version, save } => {
+            project_sizes(&ctx, version.as_deref(), *save);
+            Ok(())
+        }
+        Commands::SitePackages { version, size } => site_packages(&ctx, version, *size),
+        Commands::Init {
+            version,
+            project,
+            dir,
+        } => init_project(&ctx, version, project, dir.as_deref()),
+        Commands::Snapshot {
+            action,
+            version,
+            project,
+        } => match (action, version, project) {
+            (Some(SnapshotAction::Diff { version, project }), _, _) => {
+                diff_requirements(&ctx, version, project)
+            }
+            (None, Some(version), Some(project)) => snapshot_requirements(&ctx, version, project),
+            _ => unreachable!("clap requires a version and a project"),
+        },
+        Commands::Validate { fix } => validate_logs(&ctx, *fix),
+        Commands::Dedupe { version } => dedupe_log(&ctx, version),
+        Commands::Integrity { verify } => integrity(&ctx, *verify),
+        Commands::Doctor { fix } => doctor(&ctx, *fix),
+        Commands::Config { action } => match action {
+            ConfigAction::Get { key } => config_get(key.as_deref()),
+            ConfigAction::Set { key, value } => config_set(&ctx, key, value),
+            ConfigAction::Path => match config_path() {
+                Some(path) => {
+                    println!("{}", path.display());
+                    Ok(())
+                }
+                None => Err("Neither XDG_CONFIG_HOME nor HOME is set".into()),
+            },
+        },
+        Commands::ShowTable { sort, reverse } => {
+            show_table(&ctx, sort.map(|column| (column, *reverse)))
+        }
+    };
+
+    if let Err(err) = result {
+        ctx.exit_with_error(&err.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A context for a scratch data directory that finds no interpreters
+    /// but those in directories a test passes in.
+    pub(crate) fn test_context(data_dir: &Path) -> Context {
+        Context {
+            command: "test".to_string(),
+            yes: true,
+            dry_run: false,
+            verbose: false,
+            strict: false,
+            default_sort: None,
+            theme: TuiTheme::default(),
+            python_dirs: Vec::new(),
+            env_python_dirs: Some(Vec::new()),
+            refresh: true,
+            discovery_ttl: 0,
+            backup_count: default_backup_count(),
+            venv_roots: Vec::new(),
+            data_dir: data_dir.to_path_buf(),
+            system_dir: None,
+            read_only: false,
+            backend: StorageBackend::Json,
+            layout: LogLayout::PerVersion,
+            store: LogLayout::PerVersion.store(),
+            log_lock: RefCell::new(None),
+            exclude_patterns: Vec::new(),
+            min_version: None,
+        }
+    }
+
+    /// Creates an executable script at `path`, and the directories above it.
+    #[cfg(unix)]
+    pub(crate) fn fake_interpreter(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "#!/bin/sh\necho Python 3.0.0\n").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    pub(crate) fn log_with(version: &str, names: &[&str]) -> ProjectLog {
+        let mut log = ProjectLog::empty(version);
+        for name in names {
+            log.projects.push(Project::new(name, 1_700_000_000));
+        }
+        log
+    }
+
+    pub(crate) fn names(log: &ProjectLog) -> Vec<&str> {
+        log.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn with_layout(ctx: Context, layout: LogLayout) -> Context {
+        Context {
+            layout,
+            store: layout.store(),
+            ..ctx
+        }
+    }
+
+    #[cfg(unix)]
+    fn symlink(target: impl AsRef<Path>, link: &Path) {
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(target, link).unwrap();
+    }
+
+    fn version(text: &str) -> PythonVersion {
+        PythonVersion::parse(text).expect("a valid version")
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(version("3.9") < version("3.10"));
+        assert!(version("3.10") < version("3.11"));
+        assert!(version("2.7") < version("3.6"));
+        assert!(version("3.12.10") > version("3.12.9"));
+        assert!(version("3.12") < version("3.12.0"));
+        assert!(version("3.9.18") < version("3.10.0"));
+    }
+
+    #[test]
+    fn versions_parse_patch_and_pre_releases() {
+        assert_eq!(version("3.12.4").to_string(), "3.12.4");
+        assert_eq!(version("Python 3.11.2").to_string(), "3.11.2");
+        assert_eq!(version("3.12.4").minor_only().to_string(), "3.12");
+        // Pre-release tags are not part of the version, so a release
+        // candidate sorts with its release.
+        assert_eq!(version("3.13.0rc2").to_string(), "3.13.0");
+        assert!(version("3.12.7") < version("3.13.0rc2"));
+        assert!(version("3.13.0rc2") < version("3.13.1"));
+        assert!(PythonVersion::parse("python3").is_none());
+    }
+
+    #[test]
+    fn log_names_sort_by_version_within_implementation() {
+        let mut names = vec![
+            "3.10", "pypy3.9", "3.9", "custom", "3.11", "pypy3.10", "2.7",
+        ];
+        names.sort_by_key(|name| version_sort_key(name));
+        assert_eq!(
+            names,
+            vec!["2.7", "3.9", "3.10", "3.11", "custom", "pypy3.9", "pypy3.10"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finds_homebrew_and_framework_installs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = &fs::canonicalize(dir.path()).unwrap();
+        // Homebrew links `opt/python@3.12` to the keg in the Cellar, whose
+        // binaries point into its own framework build.
+        let keg = root.join("opt/homebrew/Cellar/python@3.12/3.12.4");
+        fake_interpreter(&keg.join("Frameworks/Python.framework/Versions/3.12/bin/python3.12"));
+        symlink(
+            "../Frameworks/Python.framework/Versions/3.12/bin/python3.12",
+            &keg.join("bin/python3.12"),
+        );
+        symlink("python3.12", &keg.join("bin/python3"));
+        symlink(&keg, &root.join("opt/homebrew/opt/python@3.12"));
+        symlink(
+            "../Cellar/python@3.12/3.12.4/bin/python3.12",
+            &root.join("opt/homebrew/bin/python3.12"),
+        );
+        // The python.org installer puts a framework build in /Library, with
+        // an Intel-only variant named like Homebrew's.
+        let versions = root.join("Library/Frameworks/Python.framework/Versions");
+        fake_interpreter(&versions.join("3.11/bin/python3.11-intel64"));
+        symlink("3.11", &versions.join("Current"));
+        fs::create_dir_all(root.join("usr/local/opt/openssl@3/bin")).unwrap();
+
+        let dirs = macos_python_dirs_in(root);
+        assert_eq!(
+            dirs,
+            vec![
+                versions.join("3.11/bin"),
+                root.join("opt/homebrew/bin"),
+                root.join("opt/homebrew/opt/python@3.12/bin"),
+            ]
+        );
+
+        let data = tempfile::tempdir().unwrap();
+        let installs = discovery::discover(&test_context(data.path()), &dirs);
+        let found: Vec<(String, PathBuf)> = installs
+            .iter()
+            .map(|install| (install.log_key(), install.path.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "3.11".to_string(),
+                    versions.join("3.11/bin/python3.11-intel64")
+                ),
+                (
+                    "3.12".to_string(),
+                    keg.join("Frameworks/Python.framework/Versions/3.12/bin/python3.12")
+                ),
+            ]
+        );
+        // Every Homebrew name for the keg's binary is folded into one install.
+        assert_eq!(installs[1].aliases.len(), 3);
+    }
+
+    #[test]
+    fn eol_table_covers_every_line_since_3_6() {
+        let lines: Vec<PythonVersion> = PYTHON_EOL_DATES
+            .iter()
+            .map(|(line, _)| version(line))
+            .collect();
+        let newest = lines.iter().max().unwrap().minor;
+        for minor in 6..=newest {
+            let line = version(&format!("3.{}", minor));
+            assert!(lines.contains(&line), "no end-of-life date for {}", line);
+            assert!(eol_date(line).is_some(), "unparsable date for {}", line);
+        }
+        // Later lines reach end-of-life later.
+        let dates: Vec<NaiveDate> = lines.iter().filter_map(|line| eol_date(*line)).collect();
+        assert_eq!(dates.len(), lines.len());
+        assert!(dates.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn interrupted_write_never_replaces_the_log() {
+        for layout in [LogLayout::PerVersion, LogLayout::Single] {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = with_layout(test_context(dir.path()), layout);
+            save_project_log(&ctx, &log_with("3.11", &["kept"])).unwrap();
+            let path = ctx.store.location(&ctx, "3.11");
+            let newer = canonical_json(&log_with("3.11", &["newer"])).unwrap();
+            fs::write(temp_path(&path), &newer[..newer.len() / 2]).unwrap();
+
+            assert_eq!(
+                names(&try_load_project_log(&ctx, "3.11").unwrap()),
+                ["kept"]
+            );
+            assert_eq!(logged_versions(&ctx), ["3.11"]);
+
+            // The next save goes through a fresh temporary file.
+            save_project_log(&ctx, &log_with("3.11", &["kept", "added"])).unwrap();
+            assert!(!temp_path(&path).exists());
+            assert_eq!(
+                names(&try_load_project_log(&ctx, "3.11").unwrap()),
+                ["added", "kept"]
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_temp_file_is_not_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        let path = ctx.store.location(&ctx, "3.11");
+        let data = canonical_json(&log_with("3.11", &["partial"])).unwrap();
+        fs::write(temp_path(&path), &data[..data.len() - 5]).unwrap();
+
+        let log = try_load_project_log(&ctx, "3.11").unwrap();
+        assert!(log.projects.is_empty());
+        assert!(logged_versions(&ctx).is_empty());
+    }
+
+    #[test]
+    fn complete_temp_file_is_recovered_when_the_log_is_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        let path = ctx.store.location(&ctx, "3.11");
+        let data = canonical_json(&log_with("3.11", &["flushed"])).unwrap();
+        fs::write(temp_path(&path), data).unwrap();
+
+        assert_eq!(
+            names(&try_load_project_log(&ctx, "3.11").unwrap()),
+            ["flushed"]
+        );
+    }
+
+    /// Writes a backup of `log` taken at `timestamp`, compressed or not.
+    fn fake_backup(ctx: &Context, log: &ProjectLog, timestamp: &str, compressed: bool) {
+        let path = ctx
+            .data_dir
+            .join(format!("{}.json.bak.{}", log.version, timestamp));
+        let data = canonical_json(log).unwrap();
+        if compressed {
+            let written = write_compressed(ctx, &path, data.as_bytes()).unwrap();
+            assert_eq!(written, gzip_path(&path), "gzip is needed for this test");
+        } else {
+            fs::write(path, data).unwrap();
+        }
+    }
+
+    #[test]
+    fn backups_beyond_the_retention_count_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context {
+            backup_count: 3,
+            ..test_context(dir.path())
+        };
+        save_project_log(&ctx, &log_with("3.11", &["a"])).unwrap();
+        assert!(list_backups(&ctx, "3.11").is_empty());
+        for second in 1..=5 {
+            let timestamp = format!("20240101-00000{}", second);
+            fake_backup(&ctx, &log_with("3.11", &[]), &timestamp, second % 2 == 0);
+        }
+
+        save_project_log(&ctx, &log_with("3.11", &["a", "b"])).unwrap();
+
+        let backups = list_backups(&ctx, "3.11");
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[..2], ["20240101-000004", "20240101-000005"]);
+        // The one just taken holds the log as it was before the save.
+        assert_eq!(
+            names(&read_backup(&ctx, "3.11", &backups[2]).unwrap()),
+            ["a"]
+        );
+        assert!(!dir.path().join("3.11.json.bak.20240101-000003").exists());
+        assert!(!dir.path().join("3.11.json.bak.20240101-000002.gz").exists());
+    }
+
+    #[test]
+    fn backups_are_listed_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        let log = log_with("3.11", &["a"]);
+        fake_backup(&ctx, &log, "20240301-120000", true);
+        fake_backup(&ctx, &log, "20231231-235959", false);
+        fake_backup(&ctx, &log, "20240115-080000", true);
+        fake_backup(&ctx, &log_with("3.1", &["a"]), "20240201-000000", false);
+        fs::write(dir.path().join("3.11.json.bak.old"), "{}").unwrap();
+
+        assert_eq!(
+            list_backups(&ctx, "3.11"),
+            ["20231231-235959", "20240115-080000", "20240301-120000"]
+        );
+        assert_eq!(list_backups(&ctx, "3.1"), ["20240201-000000"]);
+    }
+
+    #[test]
+    fn restores_a_compressed_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        save_project_log(&ctx, &log_with("3.11", &["current"])).unwrap();
+        fake_backup(&ctx, &log_with("3.11", &["old"]), "20240101-000000", true);
+        fake_backup(
+            &ctx,
+            &log_with("3.11", &["older"]),
+            "20230101-000000",
+            false,
+        );
+
+        restore_log(&ctx, "3.11", Some("20240101-000000")).unwrap();
+        assert_eq!(names(&try_load_project_log(&ctx, "3.11").unwrap()), ["old"]);
+
+        restore_log(&ctx, "3.11", Some("20230101-000000")).unwrap();
+        assert_eq!(
+            names(&try_load_project_log(&ctx, "3.11").unwrap()),
+            ["older"]
+        );
+
+        assert!(restore_log(&ctx, "3.11", Some("20220101-000000")).is_err());
+    }
+
+    /// The behaviour every `LogStore` must share, so that commands work the
+    /// same on any backend.
+    fn check_store(store: &dyn LogStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+
+        assert!(store.versions(&ctx).is_empty());
+        assert!(!store.exists(&ctx, "3.12"));
+        assert!(store.load(&ctx, "3.12").unwrap() == ProjectLog::empty("3.12"));
+
+        let mut log = log_with("3.12", &["web", "api"]);
+        log.extra.insert("synced_by".into(), "laptop".into());
+        log.projects[0].tags = vec!["work".into()];
+        log.projects[0]
+            .extra
+            .insert("color".into(), serde_json::json!({"fg": "red"}));
+        store.save(&ctx, &log).unwrap();
+        let loaded = store.load(&ctx, "3.12").unwrap();
+        assert_eq!(names(&loaded), ["api", "web"]);
+        assert_eq!(loaded.extra["synced_by"], "laptop");
+        assert_eq!(loaded.projects[1].tags, ["work"]);
+        assert_eq!(loaded.projects[1].extra["color"]["fg"], "red");
+        assert!(store.exists(&ctx, "3.12"));
+        assert!(list_backups(&ctx, "3.12").is_empty());
+
+        store.save(&ctx, &log_with("3.12", &["web"])).unwrap();
+        assert_eq!(names(&store.load(&ctx, "3.12").unwrap()), ["web"]);
+        let backups = list_backups(&ctx, "3.12");
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            names(&read_backup(&ctx, "3.12", &backups[0]).unwrap()),
+            ["api", "web"]
+        );
+
+        store.save(&ctx, &log_with("3.11", &["cli"])).unwrap();
+        let mut versions = store.versions(&ctx);
+        versions.sort();
+        assert_eq!(versions, ["3.11", "3.12"]);
+
+        store.remove(&ctx, "3.12").unwrap();
+        assert!(!store.exists(&ctx, "3.12"));
+        assert!(store.load(&ctx, "3.12").unwrap() == ProjectLog::empty("3.12"));
+        let err = store.remove(&ctx, "3.12").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let aside = store.set_aside(&ctx, "3.11", "quarantined").unwrap();
+        assert_eq!(aside, dir.path().join("3.11.json.quarantined"));
+        let kept: ProjectLog = serde_json::from_slice(&fs::read(&aside).unwrap()).unwrap();
+        assert_eq!(names(&kept), ["cli"]);
+        assert!(store.versions(&ctx).is_empty());
+    }
+
+    #[test]
+    fn json_files_meet_the_store_contract() {
+        check_store(&JsonDirStore);
+        check_store(&SingleFileStore);
+    }
+
+    #[test]
+    fn sqlite_meets_the_store_contract() {
+        check_store(&SqliteStore);
+    }
+
+    #[test]
+    fn sqlite_logs_are_migrated_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        let mut log = log_with("3.12", &["web"]);
+        log.schema_version = 1;
+        SqliteStore.save(&ctx, &log).unwrap();
+        assert_eq!(
+            SqliteStore.load(&ctx, "3.12").unwrap().schema_version,
+            LOG_SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn clear_backs_up_only_the_cleared_version() {
+        for layout in [LogLayout::PerVersion, LogLayout::Single] {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = with_layout(test_context(dir.path()), layout);
+            save_project_log(&ctx, &log_with("3.11", &["web"])).unwrap();
+            save_project_log(&ctx, &log_with("3.12", &["cli"])).unwrap();
+
+            assert!(clear_log(&ctx, "3.99", false, true).is_err());
+            clear_log(&ctx, "3.11", false, true).unwrap();
+
+            let backup: ProjectLog =
+                serde_json::from_slice(&fs::read(dir.path().join("3.11.json.bak")).unwrap())
+                    .unwrap();
+            assert_eq!(backup.version, "3.11");
+            assert_eq!(names(&backup), ["web"]);
+            assert!(load_project_log(&ctx, "3.11").projects.is_empty());
+            assert_eq!(names(&load_project_log(&ctx, "3.12")), ["cli"]);
+        }
+    }
+
+    #[test]
+    fn config_is_read_as_toml() {
+        let config = parse_config("").unwrap();
+        assert_eq!(config.backup_count, default_backup_count());
+
+        let config =
+            parse_config("backup_count = 2\nexclude_patterns = [\"-dbg$\"]\nlayout = \"single\"\n")
+                .unwrap();
+        assert_eq!(config.backup_count, 2);
+        assert_eq!(config.exclude_patterns, ["-dbg$"]);
+        assert!(config.layout == LogLayout::Single);
+
+        let Err(err) = parse_config("theme = \"dark\"\nbackup_count = 3\n") else {
+            panic!("an unknown theme was accepted");
+        };
+        assert!(err.starts_with("key 'theme': "), "{}", err);
+    }
+
+    #[test]
+    fn config_values_are_read_as_toml_or_else_strings() {
+        assert_eq!(parse_config_value("10"), toml::Value::Integer(10));
+        assert_eq!(
+            parse_config_value("[\"a\", \"b\"]"),
+            toml::Value::Array(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(parse_config_value("light"), toml::Value::from("light"));
+        assert_eq!(parse_config_value("/opt/py"), toml::Value::from("/opt/py"));
+    }
+
+    fn described(log: &mut ProjectLog, name: &str, description: &str) {
+        let project = log.projects.iter_mut().find(|p| p.name == name).unwrap();
+        project.description = Some(description.to_string());
+    }
+
+    #[test]
+    fn user_entries_shadow_system_ones_by_name() {
+        let mut system = log_with("3.11", &["base", "shared"]);
+        described(&mut system, "shared", "from the system");
+        let mut user = log_with("3.11", &["own", "shared"]);
+        described(&mut user, "shared", "mine");
+
+        let merged = merge_layers(&system, &user);
+        assert_eq!(names(&merged), ["base", "own", "shared"]);
+        assert_eq!(merged.projects[2].description.as_deref(), Some("mine"));
+        // A system-only entry comes through as it is.
+        assert!(merged.projects[0] == system.projects[0]);
+    }
+
+    #[test]
+    fn layered_writes_only_reach_the_user_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let system_dir = dir.path().join("system");
+        fs::create_dir_all(&system_dir).unwrap();
+        let system_path = system_dir.join("3.11.json");
+        let system = canonical_json(&log_with("3.11", &["base", "shared"])).unwrap();
+        fs::write(&system_path, &system).unwrap();
+        let ctx = Context {
+            system_dir: Some(system_dir),
+            store: Box::new(LayeredStore {
+                user: Box::new(JsonDirStore),
+            }),
+            ..test_context(&dir.path().join("data"))
+        };
+
+        let mut log = try_load_project_log(&ctx, "3.11").unwrap();
+        assert_eq!(names(&log), ["base", "shared"]);
+        described(&mut log, "shared", "mine");
+        log.projects.push(Project::new("own", 1_700_000_000));
+        save_project_log(&ctx, &log).unwrap();
+
+        assert_eq!(fs::read_to_string(&system_path).unwrap(), system);
+        let user = JsonDirStore.load(&ctx, "3.11").unwrap();
+        assert_eq!(names(&user), ["own", "shared"]);
+        let origins = project_origins(&ctx, &try_load_project_log(&ctx, "3.11").unwrap());
+        assert!(origins["base"] == Origin::System);
+        assert!(origins["own"] == Origin::User);
+        assert!(origins["shared"] == Origin::Shadowing);
+
+        // System entries cannot be removed through the user layer.
+        log.projects.retain(|project| project.name != "base");
+        let err = ctx.store.save(&ctx, &log).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let err = ctx.store.remove(&ctx, "3.11").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(fs::read_to_string(&system_path).unwrap(), system);
+    }
+
+    /// A log as a newer pymanager might write it, with fields this one does
+    /// not know at both levels.
+    fn future_log() -> serde_json::Value {
+        serde_json::json!({
+            "schema_version": LOG_SCHEMA_VERSION,
+            "version": "3.12",
+            "projects": [{
+                "name": "web",
+                "created_at": 1_700_000_000,
+                "last_accessed": 1_700_000_500,
+                "access_log": [1_700_000_500],
+                "workspace": {"panes": 3, "layout": "tiled"},
+                "pinned_by": ["laptop", "desktop"],
+            }],
+            "sync": {"peer": "laptop", "generation": 7},
+        })
+    }
+
+    #[test]
+    fn unknown_fields_survive_a_round_trip() {
+        let log: ProjectLog = serde_json::from_value(future_log()).unwrap();
+        assert_eq!(serde_json::to_value(&log).unwrap(), future_log());
+
+        let stores: [&dyn LogStore; 3] = [&JsonDirStore, &SingleFileStore, &SqliteStore];
+        for store in stores {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = test_context(dir.path());
+            store.save(&ctx, &log).unwrap();
+            let loaded = store.load(&ctx, "3.12").unwrap();
+            assert_eq!(serde_json::to_value(&loaded).unwrap(), future_log());
+        }
+    }
+
+    #[test]
+    fn unknown_project_fields_survive_export_and_import() {
+        let project = future_log()["projects"][0].clone();
+        let export = serde_json::json!({
+            "schema_version": EXPORT_SCHEMA_VERSION,
+            "exported_at": 1_700_001_000,
+            "versions": {"3.12": [project]},
+        });
+        let document: ExportDocument = serde_json::from_value(export.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&document).unwrap(), export);
+
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(&dir.path().join("data"));
+        let file = dir.path().join("export.json");
+        fs::write(&file, export.to_string()).unwrap();
+        import_logs(&ctx, &file).unwrap();
+        let imported = load_project_log(&ctx, "3.12");
+        assert_eq!(
+            serde_json::to_value(&imported.projects[0]).unwrap(),
+            project
+        );
+    }
+
+    fn journal_entry(project: &str) -> JournalEntry {
+        JournalEntry {
+            timestamp: 1_700_000_000,
+            command: "test".to_string(),
+            version: "3.11".to_string(),
+            project: project.to_string(),
+            action: JournalAction::Added,
+            fields: Vec::new(),
+            before: None,
+            after: None,
+        }
+    }
+
+    fn journaled(ctx: &Context) -> Vec<String> {
+        read_journal(ctx)
+            .into_iter()
+            .map(|entry| entry.project)
+            .collect()
+    }
+
+    #[test]
+    fn journal_is_read_from_compressed_and_plain_rotations() {
+        for compressed in [true, false] {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = test_context(dir.path());
+            let rotated = rotated_journal_path(&dir.path().join(JOURNAL_FILE));
+            let line = serde_json::to_string(&journal_entry("rotated")).unwrap() + "\n";
+            if compressed {
+                let written = write_compressed(&ctx, &rotated, line.as_bytes()).unwrap();
+                assert_eq!(written, gzip_path(&rotated), "gzip is needed for this test");
+            } else {
+                fs::write(&rotated, line).unwrap();
+            }
+            append_journal(&ctx, &[journal_entry("current")]).unwrap();
+
+            assert_eq!(journaled(&ctx), ["rotated", "current"]);
+        }
+    }
+
+    #[test]
+    fn a_full_journal_is_rotated_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        let path = dir.path().join(JOURNAL_FILE);
+        let line = serde_json::to_string(&journal_entry("old")).unwrap() + "\n";
+        let lines = JOURNAL_MAX_BYTES as usize / line.len() + 1;
+        fs::write(&path, line.repeat(lines)).unwrap();
+
+        append_journal(&ctx, &[journal_entry("new")]).unwrap();
+
+        assert!(gzip_path(&rotated_journal_path(&path)).exists());
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        let projects = journaled(&ctx);
+        assert_eq!(projects.len(), lines + 1);
+        assert_eq!(projects.last().unwrap(), "new");
+    }
+
+    #[test]
+    fn backups_are_read_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        fake_backup(
+            &ctx,
+            &log_with("3.11", &["packed"]),
+            "20240101-000000",
+            true,
+        );
+        fake_backup(
+            &ctx,
+            &log_with("3.11", &["plain"]),
+            "20240102-000000",
+            false,
+        );
+        assert!(backup_path(&ctx, "3.11", "20240101-000000")
+            .extension()
+            .is_some_and(|ext| ext == "gz"));
+
+        let read = |timestamp| names(&read_backup(&ctx, "3.11", timestamp).unwrap()).join(",");
+        assert_eq!(read("20240101-000000"), "packed");
+        assert_eq!(read("20240102-000000"), "plain");
+    }
+
+    #[test]
+    fn merged_entries_keep_the_widest_history() {
+        let mut first = Project::new("web", 1_700_000_000);
+        first.last_accessed = 1_700_000_900;
+        first.tags = vec!["work".into(), "django".into()];
+        first.aliases = vec!["w".into()];
+        first.notes = vec![Note {
+            text: "first".into(),
+            created_at: 1_700_000_100,
+        }];
+        first.metadata.insert("owner".into(), "old".into());
+        first.metadata.insert("team".into(), "core".into());
+        first.description = Some("Older description".into());
+        first.access_log = (0..40).map(|i| 1_700_000_000 + i).collect();
+
+        let mut second = Project::new("web", 1_600_000_000);
+        second.last_accessed = 1_800_000_000;
+        second.tags = vec!["django".into(), "api".into()];
+        second.aliases = vec!["site".into(), "w".into()];
+        second.notes = vec![Note {
+            text: "second".into(),
+            created_at: 1_600_000_100,
+        }];
+        second.metadata.insert("owner".into(), "new".into());
+        second.access_log = (20..60).map(|i| 1_700_000_000 + i).collect();
+
+        // The result does not depend on which entry comes first.
+        for merged in [
+            merge_project_entries(&first, &second),
+            merge_project_entries(&second, &first),
+        ] {
+            assert_eq!(merged.created_at, 1_600_000_000);
+            assert_eq!(merged.last_accessed, 1_800_000_000);
+            assert_eq!(merged.tags, ["django", "api", "work"]);
+            assert_eq!(merged.aliases, ["site", "w"]);
+            let notes: Vec<&str> = merged.notes.iter().map(|n| n.text.as_str()).collect();
+            assert_eq!(notes, ["second", "first"]);
+            // Conflicting metadata goes to the entry accessed last.
+            assert_eq!(merged.metadata["owner"], "new");
+            assert_eq!(merged.metadata["team"], "core");
+            assert_eq!(merged.description.as_deref(), Some("Older description"));
+            // 60 distinct accesses, of which the newest are kept.
+            let expected: Vec<u64> = (60 - ACCESS_LOG_LIMIT as u64..60)
+                .map(|i| 1_700_000_000 + i)
+                .collect();
+            assert_eq!(merged.access_log, expected);
+        }
+    }
+
+    #[test]
+    fn dedupe_folds_entries_into_the_first() {
+        let mut log = log_with("3.11", &["web", "api", "web", "cli", "web", "api"]);
+        log.projects[2].last_accessed = 1_800_000_000;
+
+        let merged = dedupe_projects(&mut log.projects);
+
+        assert_eq!(merged, [("web".to_string(), 3), ("api".to_string(), 2)]);
+        assert_eq!(names(&log), ["web", "api", "cli"]);
+        assert_eq!(log.projects[0].last_accessed, 1_800_000_000);
+    }
+
+    #[test]
+    fn relative_times_switch_units_at_the_boundaries() {
+        let now = 1_800_000_000;
+        let ago = |seconds: u64| format_relative(now - seconds, now);
+        assert_eq!(ago(0), "just now");
+        assert_eq!(ago(59), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(119), "1 minute ago");
+        assert_eq!(ago(120), "2 minutes ago");
+        assert_eq!(ago(59 * 60 + 59), "59 minutes ago");
+        assert_eq!(ago(60 * 60), "1 hour ago");
+        assert_eq!(ago(23 * 3_600 + 3_599), "23 hours ago");
+        assert_eq!(ago(24 * 3_600), "1 day ago");
+        assert_eq!(ago(29 * 86_400), "29 days ago");
+        assert_eq!(ago(30 * 86_400), "1 month ago");
+        assert_eq!(ago(364 * 86_400), "12 months ago");
+        assert_eq!(ago(365 * 86_400), "1 year ago");
+        assert_eq!(ago(3 * 365 * 86_400), "3 years ago");
+        // A clock set back makes the access look like it is in the future.
+        assert_eq!(format_relative(now + 10, now), "just now");
+    }
+
+    #[test]
+    fn deleting_selects_the_next_row_or_else_the_previous() {
+        let rows: Vec<(String, Project)> = ["a", "b", "c"]
+            .iter()
+            .map(|name| ("3.11".to_string(), Project::new(name, 0)))
+            .collect();
+        let rows: Vec<&(String, Project)> = rows.iter().collect();
+        let neighbor = |index| delete_neighbor(&rows, index).map(|(_, name)| name);
+
+        assert_eq!(neighbor(0).as_deref(), Some("b"));
+        assert_eq!(neighbor(1).as_deref(), Some("c"));
+        assert_eq!(neighbor(2).as_deref(), Some("b"));
+        assert_eq!(delete_neighbor(&rows[..1], 0), None);
+    }
+
+    #[test]
+    fn table_deletes_through_the_shared_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        save_project_log(&ctx, &log_with("3.11", &["api", "web"])).unwrap();
+
+        delete_from_table(&ctx, "3.11", "web").unwrap();
+        assert_eq!(names(&load_project_log(&ctx, "3.11")), ["api"]);
+        let err = delete_from_table(&ctx, "3.11", "web").unwrap_err();
+        assert!(err.starts_with("Project 'web' not found"), "{}", err);
+        assert!(ctx.log_lock.borrow().is_none());
+    }
+
+    #[test]
+    fn table_refuses_to_add_existing_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        save_project_log(&ctx, &log_with("3.11", &["web"])).unwrap();
+
+        add_from_table(&ctx, "3.11", "api").unwrap();
+        assert_eq!(names(&load_project_log(&ctx, "3.11")), ["api", "web"]);
+        let err = add_from_table(&ctx, "3.11", "web").unwrap_err();
+        assert!(err.contains("already exists"), "{}", err);
+        assert!(add_from_table(&ctx, "3.11", "").is_err());
+        assert!(ctx.log_lock.borrow().is_none());
+    }
+
+    #[test]
+    fn adding_an_existing_project_records_an_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path());
+        save_project_log(&ctx, &log_with("3.11", &["web"])).unwrap();
+
+        let again = Project::new("web", 1_800_000_000);
+        assert!(!insert_project(&ctx, "3.11", again, None, false).unwrap());
+        let log = load_project_log(&ctx, "3.11");
+        assert_eq!(names(&log), ["web"]);
+        assert_eq!(log.projects[0].last_accessed, 1_800_000_000);
+        assert_eq!(log.projects[0].created_at, 1_700_000_000);
+
+        let new = Project::new("api", 1_800_000_000);
+        assert!(insert_project(&ctx, "3.11", new, None, false).unwrap());
+        assert_eq!(names(&load_project_log(&ctx, "3.11")), ["api", "web"]);
+    }
+}
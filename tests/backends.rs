@@ -0,0 +1,120 @@
+mod common;
+
+use common::pymanager;
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Commands whose output should not depend on where the logs are stored.
+const SESSION: &[&[&str]] = &[
+    &["add-project", "3.11", "web", "--description", "The site"],
+    &["add-project", "3.11", "api"],
+    &["add-project", "3.12", "cli"],
+    &["tag", "add", "3.11", "web", "work"],
+    &["rename-project", "3.11", "api", "backend"],
+    &["move-project", "3.12", "cli", "3.11"],
+    &["note", "3.11", "web", "Deploys on Fridays"],
+    &["list-python-projects", "3.11", "--format", "json"],
+    &["search", "e"],
+    &["duplicates"],
+    &["remove-project", "3.11", "backend"],
+    &["list-all-projects"],
+    &["clear", "3.12"],
+];
+
+/// The output of every command in `SESSION`, with the times the sessions
+/// cannot share masked.
+fn run_session(root: &Path, backend: &str) -> Vec<String> {
+    let bin = root.join("bin");
+    let times = Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}|\b\d{10}\b").unwrap();
+    SESSION
+        .iter()
+        .map(|args| {
+            let output = pymanager(root, &bin)
+                .args(["--yes", "--backend", backend])
+                .args(*args)
+                .output()
+                .unwrap();
+            let text = format!(
+                "{:?}: {}\n{}{}",
+                args,
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            times.replace_all(&text, "<time>").into_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn commands_behave_the_same_on_every_backend() {
+    let json = tempfile::tempdir().unwrap();
+    let sqlite = tempfile::tempdir().unwrap();
+
+    let expected = run_session(json.path(), "json");
+    assert_eq!(run_session(sqlite.path(), "sqlite"), expected);
+    assert!(sqlite.path().join("data/pymanager.db").exists());
+    assert!(!sqlite.path().join("data/3.11.json").exists());
+}
+
+#[test]
+fn migrate_storage_keeps_every_log() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    let run = |args: &[&str]| {
+        let output = pymanager(root.path(), &bin).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    run(&["add-project", "3.11", "web", "--description", "The site"]);
+    run(&["add-project", "3.12", "cli"]);
+    run(&["tag", "add", "3.11", "web", "work"]);
+    let export = |backend: &str| {
+        let mut document: Value =
+            serde_json::from_str(&run(&["--backend", backend, "export"])).unwrap();
+        document.as_object_mut().unwrap().remove("exported_at");
+        document
+    };
+    let before = export("json");
+
+    let migrated = run(&["migrate-storage", "sqlite"]);
+    assert!(migrated.starts_with("Migrated 2 log(s) to the sqlite backend: 3.11, 3.12\n"));
+    assert!(!root.path().join("data/3.11.json").exists());
+    assert_eq!(export("sqlite"), before);
+    assert_eq!(export("json")["versions"], serde_json::json!({}));
+
+    run(&["--backend", "sqlite", "migrate-storage", "json"]);
+    assert_eq!(export("json"), before);
+}
+
+#[test]
+fn dry_run_merge_names_the_file_the_real_one_keeps() {
+    for (backend, layout) in [("json", "single"), ("sqlite", "per-version")] {
+        let root = tempfile::tempdir().unwrap();
+        let bin = root.path().join("bin");
+        let config = root.path().join("config/pymanager");
+        fs::create_dir_all(&config).unwrap();
+        fs::write(
+            config.join("config.toml"),
+            format!("backend = \"{}\"\nlayout = \"{}\"\n", backend, layout),
+        )
+        .unwrap();
+        let run = |args: &[&str]| {
+            let output = pymanager(root.path(), &bin).args(args).output().unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            String::from_utf8(output.stdout).unwrap()
+        };
+        run(&["add-project", "3.10", "web"]);
+
+        let dry = run(&["--dry-run", "merge-version", "3.10", "3.11"]);
+        let real = run(&["merge-version", "3.10", "3.11"]);
+
+        let kept = root.path().join("data/3.10.json.merged");
+        assert!(kept.exists());
+        let suffix = format!("old log kept as {}\n", kept.display());
+        assert!(dry.ends_with(&suffix), "{}", dry);
+        assert!(real.ends_with(&suffix), "{}", real);
+    }
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::pymanager;
+use std::fs;
+
+#[test]
+fn restore_list_shows_newest_backup_first() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    let add = pymanager(root.path(), &bin)
+        .args(["add-project", "3.11", "demo"])
+        .output()
+        .unwrap();
+    assert!(add.status.success(), "{:?}", add);
+    let data = root.path().join("data");
+    let log = fs::read_to_string(data.join("3.11.json")).unwrap();
+    for timestamp in ["20240115-080000", "20231231-235959", "20240301-120000"] {
+        fs::write(data.join(format!("3.11.json.bak.{}", timestamp)), &log).unwrap();
+    }
+
+    let output = pymanager(root.path(), &bin)
+        .args(["restore", "3.11", "--list"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let listed: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        listed,
+        ["20240301-120000", "20240115-080000", "20231231-235959"]
+    );
+}
@@ -0,0 +1,69 @@
+//! Helpers for running the pymanager binary against scratch directories.
+
+// Each test binary uses only some of these.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The pymanager binary with its data directory, config and home under
+/// `root`, searching only `python_dirs` for interpreters.
+pub fn pymanager(root: &Path, python_dirs: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pymanager"));
+    command
+        .env("HOME", root.join("home"))
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("PYMANAGER_DATA_DIR", root.join("data"))
+        .env("PYMANAGER_SYSTEM_DATA_DIR", "")
+        .env("PYMANAGER_PYTHON_DIRS", python_dirs)
+        .arg("--refresh");
+    command
+}
+
+/// Writes a fake interpreter at `path` that takes `delay` seconds to report
+/// `version`. It answers the build query at once.
+#[cfg(unix)]
+pub fn fake_python(path: &Path, version: &str, delay: u32) {
+    write_script(
+        path,
+        &format!("sleep {}; echo \"Python {}\"", delay, version),
+        "exit 1",
+    );
+}
+
+/// Writes a fake interpreter at `path` reporting `version`, whose
+/// `-m venv <dir>` creates an empty `<dir>`.
+#[cfg(unix)]
+pub fn venv_python(path: &Path, version: &str) {
+    write_script(
+        path,
+        &format!("echo \"Python {}\"", version),
+        "mkdir -p \"$3\"",
+    );
+}
+
+/// Writes a fake interpreter at `path` that never reports its version.
+#[cfg(unix)]
+pub fn hung_python(path: &Path) {
+    // `exec` so that killing the interpreter does not leave the sleep behind.
+    write_script(path, "exec sleep 600", "exit 1");
+}
+
+#[cfg(unix)]
+fn write_script(path: &Path, on_version: &str, on_venv: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        path,
+        format!(
+            "#!/bin/sh\n\
+             if [ \"$1\" = --version ]; then {}; exit; fi\n\
+             if [ \"$1\" = -m ]; then {}; exit; fi\n\
+             echo '[\"x86_64\", \"GCC\"]'\n",
+            on_version, on_venv
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
@@ -0,0 +1,49 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{pymanager, venv_python};
+use std::fs;
+
+#[test]
+fn init_removes_the_project_when_the_log_cannot_be_saved() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    venv_python(&bin.join("python3.11"), "3.11.4");
+    let run = |args: &[&str]| pymanager(root.path(), &bin).args(args).output().unwrap();
+    let added = run(&["add-project", "3.11", "existing"]);
+    assert!(added.status.success(), "{:?}", added);
+    // The log can still be read, but a save cannot write its temporary file.
+    fs::create_dir(root.path().join("data/3.11.json.tmp")).unwrap();
+
+    let project = root.path().join("demo");
+    let init = run(&["init", "3.11", "demo", "--dir", project.to_str().unwrap()]);
+
+    assert_eq!(init.status.code(), Some(1), "{:?}", init);
+    assert!(String::from_utf8(init.stderr)
+        .unwrap()
+        .starts_with("Error: could not write "));
+    assert!(!project.exists());
+}
+
+#[test]
+fn init_creates_and_registers_the_project() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    venv_python(&bin.join("python3.11"), "3.11.4");
+    let project = root.path().join("demo");
+
+    let init = pymanager(root.path(), &bin)
+        .args(["init", "3.11", "demo", "--dir", project.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert!(init.status.success(), "{:?}", init);
+    assert!(project.join(".venv").is_dir());
+    assert_eq!(
+        fs::read_to_string(project.join(".python-version")).unwrap(),
+        "3.11\n"
+    );
+    let log = fs::read_to_string(root.path().join("data/3.11.json")).unwrap();
+    assert!(log.contains("\"demo\""));
+}
@@ -0,0 +1,64 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{fake_python, hung_python, pymanager};
+use std::time::{Duration, Instant};
+
+fn probe(root: &std::path::Path, jobs: &str) -> (String, String, Duration) {
+    let start = Instant::now();
+    let output = pymanager(root, &root.join("bin"))
+        .args(["list-python-versions", "--probe", "--paths", "--jobs", jobs])
+        .output()
+        .unwrap();
+    let elapsed = start.elapsed();
+    assert!(output.status.success(), "{:?}", output);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        elapsed,
+    )
+}
+
+#[test]
+fn slow_interpreters_are_probed_in_parallel() {
+    let root = tempfile::tempdir().unwrap();
+    for minor in 10..14 {
+        fake_python(
+            &root.path().join(format!("bin/python3.{}", minor)),
+            &format!("3.{}.1", minor),
+            1,
+        );
+    }
+
+    let (serial, _, serial_time) = probe(root.path(), "1");
+    let (parallel, _, parallel_time) = probe(root.path(), "4");
+
+    assert!(serial_time >= Duration::from_secs(4), "{:?}", serial_time);
+    assert!(
+        parallel_time * 2 < serial_time,
+        "{:?} with 4 jobs against {:?} with 1",
+        parallel_time,
+        serial_time
+    );
+    // Results come out in the same order however the probes finish.
+    assert_eq!(parallel, serial);
+    for minor in 10..14 {
+        assert!(serial.contains(&format!("3.{}.1", minor)), "{}", serial);
+    }
+}
+
+#[test]
+fn a_hung_interpreter_times_out() {
+    let root = tempfile::tempdir().unwrap();
+    fake_python(&root.path().join("bin/python3.12"), "3.12.4", 1);
+    hung_python(&root.path().join("bin/python3.11"));
+
+    let (stdout, stderr, elapsed) = probe(root.path(), "4");
+
+    assert!(elapsed < Duration::from_secs(30), "{:?}", elapsed);
+    assert!(stderr.contains("no answer within"), "{}", stderr);
+    assert!(stdout.contains("3.12.4"), "{}", stdout);
+    // The hung one is still listed, under the version its name gives.
+    assert!(stdout.contains("python3.11"), "{}", stdout);
+}
@@ -0,0 +1,26 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{fake_python, pymanager};
+
+#[test]
+fn python_dirs_variable_replaces_the_search_path() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    fake_python(&bin.join("python3.42"), "3.42.0", 0);
+    // Would be found if PATH were still scanned.
+    fake_python(&root.path().join("path/python3.41"), "3.41.0", 0);
+
+    let output = pymanager(root.path(), &bin)
+        .env("PATH", root.path().join("path"))
+        .args(["list-python-versions"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Python versions found:\n3.42\n"
+    );
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::pymanager;
+use std::fs;
+
+#[test]
+fn integrity_records_no_manifest_when_read_only() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    let data = root.path().join("data");
+    fs::create_dir_all(&data).unwrap();
+
+    let output = pymanager(root.path(), &bin)
+        .args(["--read-only", "integrity"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!("Error: data directory is read-only: {}\n", data.display())
+    );
+    assert_eq!(fs::read_dir(&data).unwrap().count(), 0);
+
+    // Comparing against a manifest writes nothing, so it is still allowed.
+    let output = pymanager(root.path(), &bin)
+        .args(["--read-only", "integrity", "--verify"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("No manifest"));
+    assert_eq!(fs::read_dir(&data).unwrap().count(), 0);
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::pymanager;
+
+#[test]
+fn no_matches_is_printed_with_exit_status_1() {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    let added = pymanager(root.path(), &bin)
+        .args(["add-project", "3.11", "web"])
+        .output()
+        .unwrap();
+    assert!(added.status.success(), "{:?}", added);
+
+    for args in [&["search", "nothing"][..], &["which", "nothing"][..]] {
+        let output = pymanager(root.path(), &bin).args(args).output().unwrap();
+
+        assert_eq!(output.status.code(), Some(1), "{:?}", output);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "no matches\n");
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    }
+}